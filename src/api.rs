@@ -1,6 +1,8 @@
 pub mod admission_review;
-mod api_error;
+pub mod api_error;
+pub mod batch_review;
 pub(crate) mod handlers;
 mod raw_review;
-mod service;
+mod readiness;
+pub(crate) mod service;
 pub(crate) mod state;
@@ -1,30 +1,212 @@
+use std::fmt;
+
 use axum::{extract::rejection::JsonRejection, http::StatusCode, response::IntoResponse};
+use policy_evaluator::admission_response_handler::errors::EvaluationError;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 
+use crate::evaluation::validation_response::FailureKind;
+
+/// Maximum length, in characters, of the detail of an error
+const MAX_DETAIL_LENGTH: usize = 1024;
+
+/// A stable, machine readable, code identifying the kind of an error
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// The request is not valid
+    InvalidRequest,
+    /// The requested policy is not loaded by the server
+    PolicyNotFound,
+    /// The settings of the policy have been rejected by the policy
+    SettingsInvalid,
+    /// The policy could not be initialized
+    PolicyInitializationFailed,
+    /// The evaluation took longer than the time allowed
+    Timeout,
+    /// The Wasm module of the policy could not be instantiated, or crashed
+    WebassemblyTrap,
+    /// Any other error
+    InternalError,
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorCode::InvalidRequest => write!(f, "INVALID_REQUEST"),
+            ErrorCode::PolicyNotFound => write!(f, "POLICY_NOT_FOUND"),
+            ErrorCode::SettingsInvalid => write!(f, "SETTINGS_INVALID"),
+            ErrorCode::PolicyInitializationFailed => write!(f, "POLICY_INITIALIZATION_FAILED"),
+            ErrorCode::Timeout => write!(f, "TIMEOUT"),
+            ErrorCode::WebassemblyTrap => write!(f, "WEBASSEMBLY_TRAP"),
+            ErrorCode::InternalError => write!(f, "INTERNAL_ERROR"),
+        }
+    }
+}
+
+impl From<FailureKind> for ErrorCode {
+    fn from(failure: FailureKind) -> Self {
+        match failure {
+            FailureKind::SettingsInvalid => ErrorCode::SettingsInvalid,
+            FailureKind::Initialization => ErrorCode::PolicyInitializationFailed,
+            FailureKind::Timeout => ErrorCode::Timeout,
+            FailureKind::WebAssembly => ErrorCode::WebassemblyTrap,
+            FailureKind::Internal => ErrorCode::InternalError,
+        }
+    }
+}
+
+impl From<&EvaluationError> for ErrorCode {
+    fn from(error: &EvaluationError) -> Self {
+        match error {
+            EvaluationError::PolicyNotFound(_) => ErrorCode::PolicyNotFound,
+            EvaluationError::PolicyInitialization(_) | EvaluationError::BootstrapFailure(_) => {
+                ErrorCode::PolicyInitializationFailed
+            }
+            EvaluationError::WebAssemblyError(_) => ErrorCode::WebassemblyTrap,
+            _ => ErrorCode::InternalError,
+        }
+    }
+}
+
 #[derive(Debug)]
 /// An error that can be returned by the API
 /// and will be converted into a JSON response.
 pub(crate) struct ApiError {
     pub(crate) status: StatusCode,
+    pub(crate) code: ErrorCode,
     pub(crate) message: String,
+    /// What went wrong, as reported by the component that failed. Not reported when the
+    /// server is configured to redact the details of the errors
+    pub(crate) detail: Option<String>,
+}
+
+impl ApiError {
+    /// Build the error returned when the evaluation of a policy fails. The detail of the
+    /// error is omitted when `redact_detail` is set.
+    pub(crate) fn from_evaluation_error(
+        status: StatusCode,
+        message: &str,
+        error: &EvaluationError,
+        redact_detail: bool,
+    ) -> Self {
+        Self {
+            status,
+            code: error.into(),
+            message: message.to_owned(),
+            detail: (!redact_detail).then(|| sanitize(&error.to_string())),
+        }
+    }
+}
+
+/// Remove the control characters from the detail of an error, then truncate it
+fn sanitize(detail: &str) -> String {
+    let detail: String = detail
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    match detail.char_indices().nth(MAX_DETAIL_LENGTH) {
+        Some((index, _)) => format!("{}...", &detail[..index]),
+        None => detail,
+    }
 }
 
 impl From<JsonRejection> for ApiError {
     fn from(rejection: JsonRejection) -> Self {
         Self {
             status: rejection.status(),
+            code: ErrorCode::InvalidRequest,
             message: rejection.body_text(),
+            detail: None,
         }
     }
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
-        let payload = json!({
+        let mut payload = json!({
             "message": self.message,
             "status": self.status.as_u16(),
+            "code": self.code,
         });
+        if let Some(detail) = self.detail {
+            payload["detail"] = detail.into();
+        }
 
         (self.status, axum::Json(payload)).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case::not_found(
+        EvaluationError::PolicyNotFound("my-policy".to_owned()),
+        ErrorCode::PolicyNotFound
+    )]
+    #[case::initialization(
+        EvaluationError::PolicyInitialization("cannot download the module".to_owned()),
+        ErrorCode::PolicyInitializationFailed
+    )]
+    #[case::webassembly(
+        EvaluationError::WebAssemblyError("cannot rehydrate".to_owned()),
+        ErrorCode::WebassemblyTrap
+    )]
+    #[case::policy_group(
+        EvaluationError::CannotRehydratePolicyGroup("my-group".to_owned()),
+        ErrorCode::InternalError
+    )]
+    fn error_codes(#[case] error: EvaluationError, #[case] expected: ErrorCode) {
+        assert_eq!(ErrorCode::from(&error), expected);
+    }
+
+    #[rstest]
+    #[case::invalid_request(ErrorCode::InvalidRequest)]
+    #[case::policy_not_found(ErrorCode::PolicyNotFound)]
+    #[case::settings_invalid(ErrorCode::SettingsInvalid)]
+    #[case::policy_initialization_failed(ErrorCode::PolicyInitializationFailed)]
+    #[case::timeout(ErrorCode::Timeout)]
+    #[case::webassembly_trap(ErrorCode::WebassemblyTrap)]
+    #[case::internal_error(ErrorCode::InternalError)]
+    fn error_code_is_displayed_like_serialized(#[case] code: ErrorCode) {
+        assert_eq!(
+            serde_json::to_value(code).unwrap(),
+            serde_json::Value::String(code.to_string())
+        );
+    }
+
+    #[rstest]
+    #[case::control_characters("line 1\nline 2", "line 1 line 2")]
+    #[case::short("boom", "boom")]
+    fn detail_is_sanitized(#[case] detail: &str, #[case] expected: &str) {
+        assert_eq!(sanitize(detail), expected);
+    }
+
+    #[test]
+    fn long_detail_is_truncated() {
+        let detail = "a".repeat(MAX_DETAIL_LENGTH + 10);
+
+        assert_eq!(
+            sanitize(&detail),
+            format!("{}...", "a".repeat(MAX_DETAIL_LENGTH))
+        );
+    }
+
+    #[rstest]
+    #[case::detail(false, true)]
+    #[case::redacted(true, false)]
+    fn detail_can_be_redacted(#[case] redact_detail: bool, #[case] has_detail: bool) {
+        let error = ApiError::from_evaluation_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Something went wrong",
+            &EvaluationError::WebAssemblyError("cannot rehydrate".to_owned()),
+            redact_detail,
+        );
+
+        assert_eq!(error.code, ErrorCode::WebassemblyTrap);
+        assert_eq!(error.detail.is_some(), has_detail);
+    }
+}
@@ -0,0 +1,111 @@
+use policy_evaluator::admission_response::AdmissionResponse;
+use serde::{Deserialize, Serialize};
+
+use crate::api::{
+    admission_review::AdmissionReviewRequest,
+    api_error::{ApiError, ErrorCode},
+    service::RequestOrigin,
+};
+
+/// A request to evaluate a single AdmissionReview against many policies.
+///
+/// The policies to evaluate are either listed explicitly by `policies` or selected by
+/// matching their IDs against the `policySelector` regular expression. When neither of
+/// them is provided, the request is evaluated against all the policies.
+///
+/// The `origin` tells who sent the request, it defaults to `validate`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchReviewRequest {
+    pub admission_review: AdmissionReviewRequest,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policies: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_selector: Option<String>,
+    #[serde(default)]
+    pub origin: BatchReviewOrigin,
+}
+
+/// Who sent a batch review request. It has the same effect as sending the request to the
+/// `/validate` or the `/audit` endpoint: the policies in monitor mode, the failure policies
+/// and the metrics behave accordingly.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchReviewOrigin {
+    /// The request comes from the Kubernetes API server
+    #[default]
+    Validate,
+    /// The request comes from the audit scanner
+    Audit,
+}
+
+impl From<BatchReviewOrigin> for RequestOrigin {
+    fn from(origin: BatchReviewOrigin) -> Self {
+        match origin {
+            BatchReviewOrigin::Validate => RequestOrigin::Validate,
+            BatchReviewOrigin::Audit => RequestOrigin::Audit,
+        }
+    }
+}
+
+/// The outcome of the evaluation of a batch review request, one entry per policy
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchReviewResponse {
+    pub results: Vec<BatchReviewResult>,
+}
+
+impl BatchReviewResponse {
+    /// Returns true when all the policies accepted the request
+    pub fn all_allowed(&self) -> bool {
+        self.results.iter().all(|result| {
+            result
+                .response
+                .as_ref()
+                .is_some_and(|response| response.allowed)
+        })
+    }
+}
+
+/// The outcome of the evaluation of a single policy. Either `response` or `error` is set.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchReviewResult {
+    pub policy_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<AdmissionResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<BatchReviewError>,
+}
+
+/// The error that prevented a policy from being evaluated
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BatchReviewError {
+    pub status: u16,
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl BatchReviewResult {
+    pub(crate) fn new(policy_id: String, result: Result<AdmissionResponse, ApiError>) -> Self {
+        match result {
+            Ok(response) => Self {
+                policy_id,
+                response: Some(response),
+                error: None,
+            },
+            Err(error) => Self {
+                policy_id,
+                response: None,
+                error: Some(BatchReviewError {
+                    status: error.status.as_u16(),
+                    code: error.code,
+                    message: error.message,
+                    detail: error.detail,
+                }),
+            },
+        }
+    }
+}
@@ -5,25 +5,35 @@ use axum::{
     response::IntoResponse,
 };
 use policy_evaluator::{
-    admission_request::AdmissionRequest, admission_response::AdmissionResponse,
-    admission_response_handler::errors::EvaluationError, policy_evaluator::ValidateRequest,
+    admission_request::AdmissionRequest,
+    admission_response::AdmissionResponse,
+    admission_response_handler::{errors::EvaluationError, policy_id::PolicyID},
+    policy_evaluator::ValidateRequest,
 };
 
+use futures::future;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio::task;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{task, time};
 use tracing::{Span, debug, error};
 
 use crate::profiling::ReportGenerationError;
 use crate::{
     api::{
         admission_review::{AdmissionReviewRequest, AdmissionReviewResponse},
-        api_error::ApiError,
+        api_error::{ApiError, ErrorCode},
+        batch_review::{BatchReviewRequest, BatchReviewResponse, BatchReviewResult},
         raw_review::{RawReviewRequest, RawReviewResponse},
+        readiness::ReadinessResponse,
         service::{RequestOrigin, evaluate},
         state::ApiServerState,
     },
-    profiling,
+    evaluation::{EvaluationEnvironment, policy_info::PolicyInfo},
+    metrics, profiling,
 };
 
 // create an extractor that internally uses `axum::Json` but has a custom rejection
@@ -75,6 +85,7 @@ pub(crate) async fn audit_handler(
 
     populate_span_with_admission_request_data(&admission_review.request);
 
+    let redact_error_details = state.redact_error_details;
     let response = acquire_semaphore_and_evaluate(
         state,
         policy_id,
@@ -82,7 +93,7 @@ pub(crate) async fn audit_handler(
         RequestOrigin::Audit,
     )
     .await
-    .map_err(handle_evaluation_error)?;
+    .map_err(|e| handle_evaluation_error(e, redact_error_details))?;
 
     populate_span_with_policy_evaluation_results(&response);
 
@@ -126,6 +137,7 @@ pub(crate) async fn validate_handler(
 
     populate_span_with_admission_request_data(&admission_review.request);
 
+    let redact_error_details = state.redact_error_details;
     let response = acquire_semaphore_and_evaluate(
         state,
         policy_id,
@@ -133,7 +145,7 @@ pub(crate) async fn validate_handler(
         RequestOrigin::Validate,
     )
     .await
-    .map_err(handle_evaluation_error)?;
+    .map_err(|e| handle_evaluation_error(e, redact_error_details))?;
 
     populate_span_with_policy_evaluation_results(&response);
 
@@ -159,6 +171,7 @@ pub(crate) async fn validate_raw_handler(
 ) -> Result<Json<RawReviewResponse>, (StatusCode, ApiError)> {
     debug!(raw_review = %serde_json::to_string(&raw_review).unwrap().as_str());
 
+    let redact_error_details = state.redact_error_details;
     let response = acquire_semaphore_and_evaluate(
         state,
         policy_id,
@@ -166,15 +179,166 @@ pub(crate) async fn validate_raw_handler(
         RequestOrigin::Validate,
     )
     .await
-    .map_err(handle_evaluation_error)?;
+    .map_err(|e| handle_evaluation_error(e, redact_error_details))?;
 
     populate_span_with_policy_evaluation_results(&response);
 
     Ok(Json(RawReviewResponse::new(response)))
 }
 
-pub(crate) async fn readiness_handler() -> StatusCode {
-    StatusCode::OK
+#[tracing::instrument(
+    name = "validation_batch",
+    fields(
+        request_uid=tracing::field::Empty,
+        host=crate::config::HOSTNAME.as_str(),
+        name=tracing::field::Empty,
+        namespace=tracing::field::Empty,
+        operation=tracing::field::Empty,
+        subresource=tracing::field::Empty,
+        kind_group=tracing::field::Empty,
+        kind_version=tracing::field::Empty,
+        kind=tracing::field::Empty,
+        resource_group=tracing::field::Empty,
+        resource_version=tracing::field::Empty,
+        resource=tracing::field::Empty,
+    ),
+    skip_all)]
+/// Validate a request against many policies at once.
+/// The policies are evaluated concurrently, sharing the evaluation slots with all the other requests.
+pub(crate) async fn validate_batch_handler(
+    extract::State(state): extract::State<Arc<ApiServerState>>,
+    JsonExtractor(batch_review): JsonExtractor<BatchReviewRequest>,
+) -> Result<Json<BatchReviewResponse>, (StatusCode, ApiError)> {
+    debug!(batch_review = %serde_json::to_string(&batch_review).unwrap().as_str());
+
+    populate_span_with_admission_request_data(&batch_review.admission_review.request);
+
+    // All the policies must be evaluated by the same environment, even when
+    // the policies are reloaded in the meantime
+    let evaluation_environment = state.evaluation_environment();
+    let policy_ids = match (batch_review.policies, batch_review.policy_selector) {
+        (Some(_), Some(_)) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                ApiError {
+                    status: StatusCode::BAD_REQUEST,
+                    code: ErrorCode::InvalidRequest,
+                    message: "policies and policySelector are mutually exclusive".to_owned(),
+                    detail: None,
+                },
+            ));
+        }
+        (Some(policy_ids), None) => policy_ids,
+        (None, Some(policy_selector)) => {
+            let policy_selector = Regex::new(&policy_selector).map_err(|e| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    ApiError {
+                        status: StatusCode::BAD_REQUEST,
+                        code: ErrorCode::InvalidRequest,
+                        message: format!("invalid policySelector: {e}"),
+                        detail: None,
+                    },
+                )
+            })?;
+            evaluation_environment
+                .policy_ids()
+                .into_iter()
+                .filter(|policy_id| policy_selector.is_match(policy_id))
+                .collect()
+        }
+        (None, None) => evaluation_environment.policy_ids(),
+    };
+
+    let request = batch_review.admission_review.request;
+    let origin = batch_review.origin;
+    let evaluations = policy_ids.into_iter().map(|policy_id| {
+        let state = state.clone();
+        let evaluation_environment = evaluation_environment.clone();
+        let validate_request = ValidateRequest::AdmissionRequest(Box::new(request.clone()));
+
+        async move {
+            let result = evaluate_on_blocking_pool(
+                &state,
+                evaluation_environment,
+                policy_id.clone(),
+                validate_request,
+                origin.into(),
+            )
+            .await
+            .map_err(|e| handle_evaluation_error(e, state.redact_error_details).1);
+
+            BatchReviewResult::new(policy_id, result)
+        }
+    });
+    let results = future::join_all(evaluations).await;
+
+    Ok(Json(BatchReviewResponse { results }))
+}
+
+/// List all the policies loaded by the server, together with their status.
+pub(crate) async fn policies_handler(
+    extract::State(state): extract::State<Arc<ApiServerState>>,
+) -> Json<Vec<PolicyInfo>> {
+    Json(state.evaluation_environment().list_policies())
+}
+
+/// Show the details and the status of a policy loaded by the server.
+pub(crate) async fn policy_handler(
+    extract::State(state): extract::State<Arc<ApiServerState>>,
+    extract::Path(policy_id): extract::Path<String>,
+) -> Result<Json<PolicyInfo>, (StatusCode, ApiError)> {
+    let policy_id: PolicyID = policy_id
+        .parse()
+        .map_err(|e| handle_evaluation_error(e.into(), state.redact_error_details))?;
+
+    state
+        .evaluation_environment()
+        .get_policy_info(&policy_id)
+        .map(Json)
+        .map_err(|e| handle_evaluation_error(e, state.redact_error_details))
+}
+
+/// Report whether the Policy Server is ready to evaluate requests.
+pub(crate) async fn readiness_handler(
+    extract::State(state): extract::State<Arc<ApiServerState>>,
+) -> (StatusCode, Json<ReadinessResponse>) {
+    let response = ReadinessResponse::new(&state);
+    let status = if response.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(response))
+}
+
+/// Time given to the evaluation workers to serve the liveness probe
+const LIVENESS_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Report whether the evaluation workers are still able to process requests, by running
+/// a no-op inside of a blocking thread, like the policy evaluations.
+///
+/// The check does not wait for a slot of the evaluation pool: the pool being busy is the
+/// sign of a loaded server, not of a broken one, and restarting it would only make things
+/// worse.
+pub(crate) async fn liveness_handler() -> StatusCode {
+    let check = task::spawn_blocking(|| ());
+
+    match time::timeout(LIVENESS_PROBE_TIMEOUT, check).await {
+        Ok(Ok(())) => StatusCode::OK,
+        Ok(Err(e)) => {
+            error!(error = %e, "liveness probe failed");
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+        Err(_) => {
+            error!(
+                timeout = ?LIVENESS_PROBE_TIMEOUT,
+                "liveness probe failed: the evaluation workers did not respond in time"
+            );
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -259,22 +423,65 @@ async fn acquire_semaphore_and_evaluate(
     validate_request: ValidateRequest,
     request_origin: RequestOrigin,
 ) -> Result<AdmissionResponse, EvaluationError> {
+    let evaluation_environment = state.evaluation_environment();
+
+    evaluate_on_blocking_pool(
+        &state,
+        evaluation_environment,
+        policy_id,
+        validate_request,
+        request_origin,
+    )
+    .await
+}
+
+/// Evaluate the request once a slot of the evaluation pool is available.
+/// The evaluation is done inside of a blocking thread.
+///
+/// When the concurrency of the policy is limited, a slot reserved to the policy is acquired
+/// first: the requests waiting for a busy policy do not hold a slot of the evaluation pool,
+/// which remains available to the other policies.
+async fn evaluate_on_blocking_pool(
+    state: &ApiServerState,
+    evaluation_environment: Arc<EvaluationEnvironment>,
+    policy_id: String,
+    validate_request: ValidateRequest,
+    request_origin: RequestOrigin,
+) -> Result<AdmissionResponse, EvaluationError> {
+    let queued_at = Instant::now();
+    let policy_semaphore = state.policy_semaphore(&policy_id);
+    let _policy_permit = match policy_semaphore {
+        Some(policy_semaphore) => Some(
+            policy_semaphore
+                .acquire_owned()
+                .await
+                .expect("semaphore acquire failed"),
+        ),
+        None => None,
+    };
     let _permit = state
         .semaphore
         .acquire()
         .await
         .expect("semaphore acquire failed");
+    metrics::record_policy_queue_duration(
+        queued_at.elapsed(),
+        &metrics::PolicyEvaluationQueue {
+            policy_name: policy_id.clone(),
+        },
+    );
 
-    let state = state.clone();
+    let redact_error_details = state.redact_error_details;
     let span = Span::current();
     let response = task::spawn_blocking(move || {
         let _enter = span.enter();
 
         evaluate(
-            state.evaluation_environment.clone(),
+            evaluation_environment,
             &policy_id,
             &validate_request,
             request_origin,
+            redact_error_details,
         )
     })
     .await
@@ -318,13 +525,20 @@ fn populate_span_with_policy_evaluation_results(response: &AdmissionResponse) {
     }
 }
 
-fn handle_evaluation_error(error: EvaluationError) -> (StatusCode, ApiError) {
+/// Convert the error into the response of the API. The detail of the error is not reported
+/// when `redact_error_details` is set.
+pub(crate) fn handle_evaluation_error(
+    error: EvaluationError,
+    redact_error_details: bool,
+) -> (StatusCode, ApiError) {
     match error {
         EvaluationError::PolicyNotFound(_) => (
             StatusCode::NOT_FOUND,
             ApiError {
                 status: StatusCode::NOT_FOUND,
+                code: ErrorCode::PolicyNotFound,
                 message: error.to_string(),
+                detail: None,
             },
         ),
         err => {
@@ -332,10 +546,12 @@ fn handle_evaluation_error(error: EvaluationError) -> (StatusCode, ApiError) {
 
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                ApiError {
-                    status: StatusCode::INTERNAL_SERVER_ERROR,
-                    message: "Something went wrong".to_owned(),
-                },
+                ApiError::from_evaluation_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Something went wrong",
+                    &err,
+                    redact_error_details,
+                ),
             )
         }
     }
@@ -348,7 +564,9 @@ fn handle_pprof_error(error: ReportGenerationError) -> (StatusCode, ApiError) {
         StatusCode::INTERNAL_SERVER_ERROR,
         ApiError {
             status: StatusCode::INTERNAL_SERVER_ERROR,
+            code: ErrorCode::InternalError,
             message: "Something went wrong".to_owned(),
+            detail: None,
         },
     )
 }
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::api::state::ApiServerState;
+
+/// The body returned by the readiness probe
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReadinessResponse {
+    pub(crate) ready: bool,
+    /// The reasons why the Policy Server is not ready
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) failures: Vec<String>,
+    /// The policies that could not be initialized, indexed by their ID
+    pub(crate) policy_initialization_errors: BTreeMap<String, String>,
+    pub(crate) reloading: bool,
+    pub(crate) callback_handler_running: bool,
+}
+
+impl ReadinessResponse {
+    pub(crate) fn new(state: &ApiServerState) -> Self {
+        let policy_initialization_errors = state
+            .evaluation_environment()
+            .policy_initialization_errors();
+        let reloading = state.is_reloading();
+        let callback_handler_running = state.is_callback_handler_running();
+        let settings = &state.readiness_probe_settings;
+
+        let mut failures = Vec::new();
+        // Context aware policies cannot work without the callback handler
+        if !callback_handler_running {
+            failures.push("the callback handler is not running".to_owned());
+        }
+        if let Some(max_policy_errors) = settings.max_policy_errors
+            && policy_initialization_errors.len() > max_policy_errors
+        {
+            failures.push(format!(
+                "{} policies failed to initialize, at most {max_policy_errors} are tolerated",
+                policy_initialization_errors.len()
+            ));
+        }
+        if reloading && settings.fail_on_reload {
+            failures.push("the policies are being reloaded".to_owned());
+        }
+
+        Self {
+            ready: failures.is_empty(),
+            failures,
+            policy_initialization_errors,
+            reloading,
+            callback_handler_running,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use rstest::*;
+    use tokio::sync::Semaphore;
+
+    use crate::{api::state::ReadinessProbeSettings, evaluation::EvaluationEnvironment};
+
+    fn build_state(
+        policy_errors: usize,
+        readiness_probe_settings: ReadinessProbeSettings,
+    ) -> ApiServerState {
+        let mut mock_evaluation_environment = EvaluationEnvironment::default();
+        mock_evaluation_environment
+            .expect_policy_initialization_errors()
+            .returning(move || {
+                (0..policy_errors)
+                    .map(|i| (format!("policy-{i}"), "boom".to_owned()))
+                    .collect()
+            });
+        mock_evaluation_environment
+            .expect_policies_max_concurrency()
+            .returning(HashMap::new);
+
+        ApiServerState::new(
+            Semaphore::new(1),
+            mock_evaluation_environment,
+            readiness_probe_settings,
+            false,
+        )
+    }
+
+    #[rstest]
+    #[case::no_threshold(3, None, true)]
+    #[case::below_threshold(1, Some(1), true)]
+    #[case::above_threshold(2, Some(1), false)]
+    #[case::no_errors_tolerated(1, Some(0), false)]
+    fn readiness_with_policy_errors(
+        #[case] policy_errors: usize,
+        #[case] max_policy_errors: Option<usize>,
+        #[case] expected_ready: bool,
+    ) {
+        let state = build_state(
+            policy_errors,
+            ReadinessProbeSettings {
+                max_policy_errors,
+                fail_on_reload: false,
+            },
+        );
+
+        let response = ReadinessResponse::new(&state);
+
+        assert_eq!(response.ready, expected_ready);
+        assert_eq!(response.failures.is_empty(), expected_ready);
+        assert_eq!(response.policy_initialization_errors.len(), policy_errors);
+    }
+
+    #[rstest]
+    #[case::fail_on_reload(true, false)]
+    #[case::ignore_reload(false, true)]
+    fn readiness_while_reloading(#[case] fail_on_reload: bool, #[case] expected_ready: bool) {
+        let state = build_state(
+            0,
+            ReadinessProbeSettings {
+                max_policy_errors: None,
+                fail_on_reload,
+            },
+        );
+        state.set_reloading(true);
+
+        let response = ReadinessResponse::new(&state);
+
+        assert!(response.reloading);
+        assert_eq!(response.ready, expected_ready);
+    }
+
+    #[test]
+    fn not_ready_when_callback_handler_is_stopped() {
+        let state = build_state(0, ReadinessProbeSettings::default());
+        state.set_callback_handler_stopped();
+
+        let response = ReadinessResponse::new(&state);
+
+        assert!(!response.ready);
+        assert!(!response.callback_handler_running);
+    }
+}
@@ -8,8 +8,20 @@ use policy_evaluator::{
     policy_evaluator::ValidateRequest,
 };
 use tokio::time::Instant;
+use tracing::warn;
+
+use crate::{
+    api::api_error::ErrorCode,
+    config::FailurePolicy,
+    evaluation::{
+        EvaluationEnvironment,
+        validation_response::{FailureKind, ValidationResponse},
+    },
+    metrics,
+};
 
-use crate::{evaluation::EvaluationEnvironment, metrics};
+/// The message reporting a failure when its details are not disclosed
+const FAILURE_MESSAGE: &str = "the evaluation of the policy failed";
 
 pub(crate) enum RequestOrigin {
     Validate,
@@ -30,6 +42,7 @@ pub(crate) fn evaluate(
     policy_id: &str,
     validate_request: &ValidateRequest,
     request_origin: RequestOrigin,
+    redact_error_details: bool,
 ) -> Result<AdmissionResponse, EvaluationError> {
     let start_time = Instant::now();
     let policy_id: PolicyID = policy_id.parse()?;
@@ -51,6 +64,7 @@ pub(crate) fn evaluate(
             mutated: false,
             request_origin: request_origin.to_string(),
             error_code: None,
+            skip_reason: None,
         };
         metrics::record_policy_latency(start_time.elapsed(), &policy_evaluation_metric);
         metrics::add_policy_evaluation(&policy_evaluation_metric);
@@ -66,27 +80,90 @@ pub(crate) fn evaluate(
         });
     }
 
-    let vanilla_validation_response = match evaluation_environment
-        .clone()
-        .validate(&policy_id, validate_request)
+    // Accept the requests that do not satisfy the match conditions of the policy,
+    // without instantiating it
+    if let ValidateRequest::AdmissionRequest(adm_req) = validate_request
+        && !evaluation_environment.request_matches_conditions(&policy_id, adm_req)
     {
-        Ok(validation_response) => validation_response,
-        Err(EvaluationError::PolicyInitialization(error)) => {
-            let policy_initialization_error_metric = metrics::PolicyInitializationError {
-                policy_name: policy_id.to_string(),
-                initialization_error: error.to_string(),
+        let policy_evaluation_metric = metrics::PolicyEvaluation {
+            policy_name: policy_id.to_string(),
+            policy_mode: evaluation_environment.get_policy_mode(&policy_id)?.into(),
+            resource_namespace: adm_req.namespace.clone(),
+            resource_kind: adm_req.request_kind.clone().unwrap_or_default().kind,
+            resource_request_operation: adm_req.operation.clone(),
+            accepted: true,
+            mutated: false,
+            request_origin: request_origin.to_string(),
+            error_code: None,
+            skip_reason: Some("match_conditions".to_owned()),
+        };
+        metrics::record_policy_latency(start_time.elapsed(), &policy_evaluation_metric);
+        metrics::add_policy_evaluation(&policy_evaluation_metric);
+
+        return Ok(AdmissionResponse {
+            uid: validate_request.uid().to_owned(),
+            allowed: true,
+            ..Default::default()
+        });
+    }
+
+    let vanilla_validation_response = match evaluation_environment.clone().validate(
+        &policy_id,
+        validate_request,
+        matches!(request_origin, RequestOrigin::Audit),
+    ) {
+        Ok(ValidationResponse {
+            response,
+            failure: None,
+        }) => response,
+        Ok(ValidationResponse {
+            response,
+            failure: Some(failure),
+        }) => {
+            let error = response
+                .status
+                .as_ref()
+                .and_then(|status| status.message.clone())
+                .unwrap_or_default();
+            let response = match apply_failure_policy(
+                &evaluation_environment,
+                &policy_id,
+                validate_request,
+                &request_origin,
+                &error,
+                redact_error_details,
+            ) {
+                Some(response) => response,
+                None if redact_error_details => redact_failure_details(response, &policy_id),
+                None => response,
             };
+            let response = with_error_code(response, failure);
 
-            metrics::add_policy_evaluation(&policy_initialization_error_metric);
+            if failure.is_initialization() {
+                let policy_initialization_error_metric = metrics::PolicyInitializationError {
+                    policy_name: policy_id.to_string(),
+                    initialization_error: error,
+                };
 
-            return Ok(AdmissionResponse::reject(
-                validate_request.uid().to_owned(),
-                error.to_string(),
-                500,
-            ));
-        }
+                metrics::add_policy_evaluation(&policy_initialization_error_metric);
+
+                // The settings of the policy are not registered, the response cannot go
+                // through the usual processing
+                return Ok(response);
+            }
 
-        Err(error) => return Err(error),
+            response
+        }
+        Err(error @ EvaluationError::PolicyNotFound(_)) => return Err(error),
+        Err(error) => apply_failure_policy(
+            &evaluation_environment,
+            &policy_id,
+            validate_request,
+            &request_origin,
+            &error.to_string(),
+            redact_error_details,
+        )
+        .ok_or(error)?,
     };
 
     let policy_mode = evaluation_environment.get_policy_mode(&policy_id)?;
@@ -129,6 +206,7 @@ pub(crate) fn evaluate(
                 mutated,
                 request_origin: request_origin.to_string(),
                 error_code,
+                skip_reason: None,
             };
             metrics::record_policy_latency(policy_evaluation_duration, &policy_evaluation_metric);
             metrics::add_policy_evaluation(&policy_evaluation_metric);
@@ -151,6 +229,84 @@ pub(crate) fn evaluate(
     Ok(validation_response)
 }
 
+/// Turn an evaluation failure into a response, according to the failure policy of the policy.
+///
+/// Returns `None` when the policy does not define a failure policy, or when the request comes
+/// from the audit scanner: the failure is then reported as usual.
+///
+/// The error is not reported inside of the response when `redact_error_details` is set, it
+/// is only logged.
+fn apply_failure_policy(
+    evaluation_environment: &EvaluationEnvironment,
+    policy_id: &PolicyID,
+    validate_request: &ValidateRequest,
+    request_origin: &RequestOrigin,
+    error: &str,
+    redact_error_details: bool,
+) -> Option<AdmissionResponse> {
+    if matches!(request_origin, RequestOrigin::Audit) {
+        return None;
+    }
+    let failure_policy = evaluation_environment.get_policy_failure_policy(policy_id)?;
+
+    metrics::add_policy_evaluation_failure(&metrics::PolicyEvaluationFailure {
+        policy_name: policy_id.to_string(),
+        failure_policy: failure_policy.to_string(),
+    });
+
+    let uid = validate_request.uid().to_owned();
+    let detail = if redact_error_details {
+        String::new()
+    } else {
+        format!(": {error}")
+    };
+    let response = match failure_policy {
+        FailurePolicy::Ignore => {
+            warn!(
+                %policy_id,
+                error,
+                "policy evaluation failed, the request is accepted because of the Ignore failure policy"
+            );
+            AdmissionResponse {
+                uid,
+                allowed: true,
+                warnings: Some(vec![format!(
+                    "{policy_id}: {FAILURE_MESSAGE}, the request has been accepted{detail}"
+                )]),
+                ..Default::default()
+            }
+        }
+        FailurePolicy::Fail => {
+            AdmissionResponse::reject(uid, format!("{policy_id}: {FAILURE_MESSAGE}{detail}"), 500)
+        }
+    };
+
+    Some(response)
+}
+
+/// Replace the message of the rejection reporting a failure with a generic one. The code of
+/// the failure, set by `with_error_code`, still tells the failures apart
+fn redact_failure_details(
+    mut response: AdmissionResponse,
+    policy_id: &PolicyID,
+) -> AdmissionResponse {
+    if let Some(status) = response.status.as_mut() {
+        status.message = Some(format!("{policy_id}: {FAILURE_MESSAGE}"));
+    }
+
+    response
+}
+
+/// Report the code of the failure inside of the `reason` of the rejection, this lets the
+/// clients tell the failures apart without parsing the message
+fn with_error_code(mut response: AdmissionResponse, failure: FailureKind) -> AdmissionResponse {
+    if let Some(status) = response.status.as_mut() {
+        status.reason = Some(ErrorCode::from(failure).to_string());
+    }
+
+    response
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -173,12 +329,12 @@ mod tests {
         let mut mock_evaluation_environment = EvaluationEnvironment::default();
         mock_evaluation_environment
             .expect_validate()
-            .returning(|_policy_id, request| {
-                Ok(AdmissionResponse {
+            .returning(|_policy_id, request, _trace| {
+                Ok(ValidationResponse::verdict(AdmissionResponse {
                     uid: request.uid().to_owned(),
                     allowed: true,
                     ..Default::default()
-                })
+                }))
             });
 
         mock_evaluation_environment
@@ -190,6 +346,9 @@ mod tests {
         mock_evaluation_environment
             .expect_should_always_accept_requests_made_inside_of_namespace()
             .returning(|_namespace| false);
+        mock_evaluation_environment
+            .expect_request_matches_conditions()
+            .returning(|_policy_id, _request| true);
         mock_evaluation_environment
             .expect_get_policy_custom_rejection_message()
             .returning(|_policy_id| Ok(None));
@@ -209,15 +368,15 @@ mod tests {
         allowed_namespace: String,
     ) -> EvaluationEnvironment {
         let mut mock_evaluation_environment = EvaluationEnvironment::default();
-        mock_evaluation_environment
-            .expect_validate()
-            .returning(move |_policy_id, request| {
-                Ok(AdmissionResponse::reject(
+        mock_evaluation_environment.expect_validate().returning(
+            move |_policy_id, request, _trace| {
+                Ok(ValidationResponse::verdict(AdmissionResponse::reject(
                     request.uid().to_owned(),
                     rejection_details.message.clone(),
                     rejection_details.code,
-                ))
-            });
+                )))
+            },
+        );
         mock_evaluation_environment
             .expect_get_policy_mode()
             .returning(move |_policy_id| Ok(policy_mode.clone()));
@@ -227,6 +386,12 @@ mod tests {
         mock_evaluation_environment
             .expect_should_always_accept_requests_made_inside_of_namespace()
             .returning(move |namespace| namespace == allowed_namespace);
+        mock_evaluation_environment
+            .expect_get_policy_failure_policy()
+            .returning(|_policy_id| None);
+        mock_evaluation_environment
+            .expect_request_matches_conditions()
+            .returning(|_policy_id, _request| true);
         mock_evaluation_environment
             .expect_get_policy_custom_rejection_message()
             .returning(|_policy_id| Ok(None));
@@ -255,6 +420,7 @@ mod tests {
             policy_id,
             &validate_request,
             request_origin,
+            false,
         )
         .unwrap();
         assert!(response.allowed);
@@ -289,6 +455,7 @@ mod tests {
             policy_id,
             &validate_request,
             request_origin,
+            false,
         )
         .unwrap();
 
@@ -316,6 +483,7 @@ mod tests {
             policy_id,
             &validate_request,
             RequestOrigin::Validate,
+            false,
         )
         .unwrap();
 
@@ -342,6 +510,7 @@ mod tests {
             policy_id,
             &validate_request,
             RequestOrigin::Validate,
+            false,
         )
         .unwrap();
 
@@ -379,6 +548,176 @@ mod tests {
             policy_id,
             &validate_request,
             request_origin,
+            false,
+        )
+        .unwrap();
+
+        assert!(response.allowed);
+        assert!(response.status.is_none());
+    }
+
+    fn create_evaluation_environment_that_fails(
+        failure_policy: Option<FailurePolicy>,
+    ) -> EvaluationEnvironment {
+        let mut mock_evaluation_environment = EvaluationEnvironment::default();
+        mock_evaluation_environment
+            .expect_validate()
+            .returning(|_policy_id, request, _trace| {
+                Ok(ValidationResponse::failure(
+                    request.uid().to_owned(),
+                    FailureKind::WebAssembly,
+                    "cannot rehydrate".to_owned(),
+                ))
+            });
+        mock_evaluation_environment
+            .expect_get_policy_failure_policy()
+            .returning(move |_policy_id| failure_policy);
+        mock_evaluation_environment
+            .expect_get_policy_mode()
+            .returning(|_policy_id| Ok(PolicyMode::Protect));
+        mock_evaluation_environment
+            .expect_get_policy_allowed_to_mutate()
+            .returning(|_policy_id| Ok(false));
+        mock_evaluation_environment
+            .expect_should_always_accept_requests_made_inside_of_namespace()
+            .returning(|_namespace| false);
+        mock_evaluation_environment
+            .expect_request_matches_conditions()
+            .returning(|_policy_id, _request| true);
+        mock_evaluation_environment
+            .expect_get_policy_custom_rejection_message()
+            .returning(|_policy_id| Ok(None));
+
+        mock_evaluation_environment
+    }
+
+    #[rstest]
+    #[test]
+    #[case::ignore(Some(FailurePolicy::Ignore), RequestOrigin::Validate, true)]
+    #[case::fail(Some(FailurePolicy::Fail), RequestOrigin::Validate, false)]
+    #[case::not_set(None, RequestOrigin::Validate, false)]
+    #[case::audit(Some(FailurePolicy::Ignore), RequestOrigin::Audit, false)]
+    fn evaluate_applies_the_failure_policy_to_evaluation_failures(
+        #[case] failure_policy: Option<FailurePolicy>,
+        #[case] request_origin: RequestOrigin,
+        #[case] expected_allowed: bool,
+        #[values(false, true)] redact_error_details: bool,
+    ) {
+        let evaluation_environment = create_evaluation_environment_that_fails(failure_policy);
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluate(
+            Arc::new(evaluation_environment),
+            "test_policy1",
+            &validate_request,
+            request_origin,
+            redact_error_details,
+        )
+        .unwrap();
+
+        assert_eq!(response.allowed, expected_allowed);
+        let reported = if expected_allowed {
+            response.warnings.unwrap()[0].clone()
+        } else {
+            let status = response.status.unwrap();
+            // The code of the failure is reported even when its details are redacted
+            assert_eq!(status.reason, Some("WEBASSEMBLY_TRAP".to_owned()));
+            status.message.unwrap()
+        };
+        assert!(
+            reported.contains(FAILURE_MESSAGE),
+            "unexpected report: {reported}"
+        );
+        assert_eq!(
+            reported.contains("cannot rehydrate"),
+            !redact_error_details,
+            "unexpected report: {reported}"
+        );
+    }
+
+    #[rstest]
+    #[test]
+    #[case::timeout(Some(FailureKind::Timeout), true)]
+    #[case::rejection(None, false)]
+    fn evaluate_applies_the_failure_policy_only_to_failures(
+        #[case] failure: Option<FailureKind>,
+        #[case] expected_allowed: bool,
+    ) {
+        let mut mock_evaluation_environment = EvaluationEnvironment::default();
+        mock_evaluation_environment.expect_validate().returning(
+            move |_policy_id, request, _trace| {
+                Ok(ValidationResponse {
+                    response: AdmissionResponse::reject(
+                        request.uid().to_owned(),
+                        "execution interrupted".to_owned(),
+                        500,
+                    ),
+                    failure,
+                })
+            },
+        );
+        mock_evaluation_environment
+            .expect_get_policy_failure_policy()
+            .returning(|_policy_id| Some(FailurePolicy::Ignore));
+        mock_evaluation_environment
+            .expect_get_policy_mode()
+            .returning(|_policy_id| Ok(PolicyMode::Protect));
+        mock_evaluation_environment
+            .expect_get_policy_allowed_to_mutate()
+            .returning(|_policy_id| Ok(false));
+        mock_evaluation_environment
+            .expect_should_always_accept_requests_made_inside_of_namespace()
+            .returning(|_namespace| false);
+        mock_evaluation_environment
+            .expect_request_matches_conditions()
+            .returning(|_policy_id, _request| true);
+        mock_evaluation_environment
+            .expect_get_policy_custom_rejection_message()
+            .returning(|_policy_id| Ok(None));
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluate(
+            Arc::new(mock_evaluation_environment),
+            "test_policy1",
+            &validate_request,
+            RequestOrigin::Validate,
+            false,
+        )
+        .unwrap();
+
+        // A 500 rejection that is not a failure is the verdict of the policy
+        assert_eq!(response.allowed, expected_allowed);
+    }
+
+    #[rstest]
+    #[test]
+    #[case(RequestOrigin::Validate)]
+    #[case(RequestOrigin::Audit)]
+    fn evaluate_skips_policy_when_request_does_not_satisfy_match_conditions(
+        #[case] request_origin: RequestOrigin,
+    ) {
+        let mut mock_evaluation_environment = EvaluationEnvironment::default();
+        mock_evaluation_environment
+            .expect_should_always_accept_requests_made_inside_of_namespace()
+            .returning(|_namespace| false);
+        mock_evaluation_environment
+            .expect_request_matches_conditions()
+            .returning(|_policy_id, _request| false);
+        mock_evaluation_environment
+            .expect_get_policy_mode()
+            .returning(|_policy_id| Ok(PolicyMode::Protect));
+        mock_evaluation_environment.expect_validate().never();
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluate(
+            Arc::new(mock_evaluation_environment),
+            "test_policy1",
+            &validate_request,
+            request_origin,
+            false,
         )
         .unwrap();
 
@@ -1,9 +1,315 @@
-use tokio::sync::Semaphore;
+use tokio::{sync::Semaphore, task::JoinHandle};
 
 use crate::evaluation::EvaluationEnvironment;
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+/// Settings that influence the outcome of the readiness probe
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ReadinessProbeSettings {
+    /// Number of policies that can fail to initialize before the Policy Server is
+    /// reported as not ready. When not set, initialization errors are just reported.
+    pub(crate) max_policy_errors: Option<usize>,
+    /// Report the Policy Server as not ready while the policies are being reloaded
+    pub(crate) fail_on_reload: bool,
+}
+
+/// The semaphore limiting the number of evaluations of a policy running at the same time
+struct PolicySemaphore {
+    semaphore: Arc<Semaphore>,
+    /// The limit of the policy. The semaphore holds `pending_shrink` more permits until
+    /// the shrink task removes them
+    permits: usize,
+    /// The number of permits the shrink task still has to remove from the semaphore
+    pending_shrink: Arc<Mutex<usize>>,
+    /// The task removing the surplus permits of a lowered limit, once they are released by
+    /// the running evaluations
+    shrink_task: Option<JoinHandle<()>>,
+}
+
+impl PolicySemaphore {
+    fn new(permits: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(permits)),
+            permits,
+            pending_shrink: Arc::new(Mutex::new(0)),
+            shrink_task: None,
+        }
+    }
+
+    /// Set the limit of the policy
+    fn resize(&mut self, permits: usize) {
+        // Stop the pending shrink first: the permits it did not remove yet are still part
+        // of the semaphore
+        let pending_shrink = std::mem::take(
+            &mut *self
+                .pending_shrink
+                .lock()
+                .expect("cannot acquire lock on the pending shrink"),
+        );
+        if let Some(shrink_task) = self.shrink_task.take() {
+            shrink_task.abort();
+        }
+        let current_permits = self.permits + pending_shrink;
+
+        if permits > current_permits {
+            self.semaphore.add_permits(permits - current_permits);
+        } else if permits < current_permits {
+            let surplus = current_permits - permits;
+            let forgotten = self.semaphore.forget_permits(surplus);
+            if forgotten < surplus {
+                self.shrink(surplus - forgotten);
+            }
+        }
+        self.permits = permits;
+    }
+
+    /// Remove `surplus` permits from the semaphore as soon as they are released
+    fn shrink(&mut self, surplus: usize) {
+        *self
+            .pending_shrink
+            .lock()
+            .expect("cannot acquire lock on the pending shrink") = surplus;
+
+        let semaphore = self.semaphore.clone();
+        let pending_shrink = self.pending_shrink.clone();
+        self.shrink_task = Some(tokio::spawn(async move {
+            loop {
+                let Ok(permit) = semaphore.acquire().await else {
+                    return;
+                };
+                let mut pending_shrink = pending_shrink
+                    .lock()
+                    .expect("cannot acquire lock on the pending shrink");
+                // The shrink has been stopped by a resize, the permit is given back
+                if *pending_shrink == 0 {
+                    return;
+                }
+                permit.forget();
+                *pending_shrink -= 1;
+                if *pending_shrink == 0 {
+                    return;
+                }
+            }
+        }));
+    }
+}
+
+impl Drop for PolicySemaphore {
+    fn drop(&mut self) {
+        if let Some(shrink_task) = self.shrink_task.take() {
+            shrink_task.abort();
+        }
+    }
+}
 
 pub(crate) struct ApiServerState {
     pub(crate) semaphore: Semaphore,
-    pub(crate) evaluation_environment: Arc<EvaluationEnvironment>,
+    /// The semaphores of the policies defining `maxConcurrency`, indexed by policy ID. They
+    /// outlive the `EvaluationEnvironment`: the evaluations running while the policies are
+    /// reloaded count against the limits of the new policies.
+    policy_semaphores: RwLock<HashMap<String, PolicySemaphore>>,
+    pub(crate) readiness_probe_settings: ReadinessProbeSettings,
+    /// Do not report the details of the evaluation errors inside of the responses
+    pub(crate) redact_error_details: bool,
+    /// The `EvaluationEnvironment` is wrapped inside of a lock because it can be replaced
+    /// at runtime when the policies are reloaded
+    evaluation_environment: RwLock<Arc<EvaluationEnvironment>>,
+    /// Set while a new `EvaluationEnvironment` is being built
+    reloading: AtomicBool,
+    /// Set once the task running the `CallbackHandler` exits
+    callback_handler_stopped: AtomicBool,
+}
+
+impl ApiServerState {
+    pub(crate) fn new(
+        semaphore: Semaphore,
+        evaluation_environment: EvaluationEnvironment,
+        readiness_probe_settings: ReadinessProbeSettings,
+        redact_error_details: bool,
+    ) -> Self {
+        let state = Self {
+            semaphore,
+            policy_semaphores: RwLock::new(HashMap::new()),
+            readiness_probe_settings,
+            redact_error_details,
+            evaluation_environment: RwLock::new(Arc::new(evaluation_environment)),
+            reloading: AtomicBool::new(false),
+            callback_handler_stopped: AtomicBool::new(false),
+        };
+        state.resize_policy_semaphores(state.evaluation_environment().policies_max_concurrency());
+
+        state
+    }
+
+    /// Returns the `EvaluationEnvironment` currently in use.
+    ///
+    /// The caller keeps a reference to the returned instance, hence the evaluations that are
+    /// in progress while the policies are reloaded are completed using the previous
+    /// `EvaluationEnvironment`.
+    pub(crate) fn evaluation_environment(&self) -> Arc<EvaluationEnvironment> {
+        self.evaluation_environment
+            .read()
+            .expect("cannot acquire read lock on the evaluation environment")
+            .clone()
+    }
+
+    /// Atomically replace the `EvaluationEnvironment` used to evaluate the incoming requests.
+    /// The semaphores of the policies are resized to their new `maxConcurrency`.
+    pub(crate) fn replace_evaluation_environment(
+        &self,
+        evaluation_environment: EvaluationEnvironment,
+    ) {
+        self.resize_policy_semaphores(evaluation_environment.policies_max_concurrency());
+        let mut current = self
+            .evaluation_environment
+            .write()
+            .expect("cannot acquire write lock on the evaluation environment");
+        *current = Arc::new(evaluation_environment);
+    }
+
+    /// Returns the semaphore limiting the number of evaluations of the given policy running
+    /// at the same time. `None` when the concurrency of the policy is not limited
+    pub(crate) fn policy_semaphore(&self, policy_id: &str) -> Option<Arc<Semaphore>> {
+        self.policy_semaphores
+            .read()
+            .expect("cannot acquire read lock on the policy semaphores")
+            .get(policy_id)
+            .map(|policy_semaphore| policy_semaphore.semaphore.clone())
+    }
+
+    /// Resize the semaphores of the policies to the given limits, indexed by policy ID. The
+    /// semaphores of the policies that are not limited anymore are dropped.
+    ///
+    /// The permits held by the running evaluations cannot be taken back: when a limit is
+    /// lowered, the surplus permits are removed as soon as they are released.
+    fn resize_policy_semaphores(&self, limits: HashMap<String, NonZeroUsize>) {
+        let mut policy_semaphores = self
+            .policy_semaphores
+            .write()
+            .expect("cannot acquire write lock on the policy semaphores");
+        policy_semaphores.retain(|policy_id, _| limits.contains_key(policy_id));
+
+        for (policy_id, limit) in limits {
+            let permits = limit.get();
+            match policy_semaphores.get_mut(&policy_id) {
+                Some(policy_semaphore) => policy_semaphore.resize(permits),
+                None => {
+                    policy_semaphores.insert(policy_id, PolicySemaphore::new(permits));
+                }
+            }
+        }
+    }
+
+    pub(crate) fn is_reloading(&self) -> bool {
+        self.reloading.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_reloading(&self, reloading: bool) {
+        self.reloading.store(reloading, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_callback_handler_running(&self) -> bool {
+        !self.callback_handler_stopped.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_callback_handler_stopped(&self) {
+        self.callback_handler_stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluation_environment(
+        policies_max_concurrency: HashMap<String, NonZeroUsize>,
+    ) -> EvaluationEnvironment {
+        let mut mock_evaluation_environment = EvaluationEnvironment::default();
+        mock_evaluation_environment
+            .expect_policies_max_concurrency()
+            .returning(move || policies_max_concurrency.clone());
+
+        mock_evaluation_environment
+    }
+
+    fn limits(limits: &[(&str, usize)]) -> HashMap<String, NonZeroUsize> {
+        limits
+            .iter()
+            .map(|(policy_id, limit)| (policy_id.to_string(), NonZeroUsize::new(*limit).unwrap()))
+            .collect()
+    }
+
+    fn available_permits(state: &ApiServerState, policy_id: &str) -> Option<usize> {
+        state
+            .policy_semaphore(policy_id)
+            .map(|semaphore| semaphore.available_permits())
+    }
+
+    #[tokio::test]
+    async fn policy_semaphores_are_resized_on_reload() {
+        let state = ApiServerState::new(
+            Semaphore::new(1),
+            evaluation_environment(limits(&[("grown", 1), ("shrunk", 3), ("removed", 1)])),
+            ReadinessProbeSettings::default(),
+            false,
+        );
+        let shrunk = state.policy_semaphore("shrunk").unwrap();
+        let running_evaluation = shrunk.clone().acquire_owned().await.unwrap();
+
+        state.replace_evaluation_environment(evaluation_environment(limits(&[
+            ("grown", 2),
+            ("shrunk", 1),
+            ("added", 1),
+        ])));
+
+        assert_eq!(available_permits(&state, "grown"), Some(2));
+        assert_eq!(available_permits(&state, "added"), Some(1));
+        assert_eq!(available_permits(&state, "removed"), None);
+        // The permit held by the running evaluation is the only one left
+        assert_eq!(available_permits(&state, "shrunk"), Some(0));
+        assert!(Arc::ptr_eq(
+            &shrunk,
+            &state.policy_semaphore("shrunk").unwrap()
+        ));
+        drop(running_evaluation);
+        assert_eq!(available_permits(&state, "shrunk"), Some(1));
+    }
+
+    #[tokio::test]
+    async fn policy_semaphore_grows_while_being_shrunk() {
+        let state = ApiServerState::new(
+            Semaphore::new(1),
+            evaluation_environment(limits(&[("policy", 3)])),
+            ReadinessProbeSettings::default(),
+            false,
+        );
+        let semaphore = state.policy_semaphore("policy").unwrap();
+        let mut running_evaluations = Vec::new();
+        for _ in 0..3 {
+            running_evaluations.push(semaphore.clone().acquire_owned().await.unwrap());
+        }
+
+        // None of the 2 surplus permits can be removed while the evaluations are running
+        state.replace_evaluation_environment(evaluation_environment(limits(&[("policy", 1)])));
+        tokio::task::yield_now().await;
+        running_evaluations.pop();
+        tokio::task::yield_now().await;
+        assert_eq!(available_permits(&state, "policy"), Some(0));
+
+        // The permit still to be removed is taken into account by the new limit, the aborted
+        // shrink task gives back the permits it was waiting for
+        state.replace_evaluation_environment(evaluation_environment(limits(&[("policy", 3)])));
+        tokio::task::yield_now().await;
+        assert_eq!(available_permits(&state, "policy"), Some(1));
+        running_evaluations.clear();
+        tokio::task::yield_now().await;
+        assert_eq!(available_permits(&state, "policy"), Some(3));
+    }
 }
@@ -1,5 +1,12 @@
+use std::{collections::BTreeMap, fs::File, path::PathBuf};
+
+use anyhow::{Result, anyhow};
 use clap::builder::PossibleValue;
-use clap::{Arg, ArgAction, Command, crate_authors, crate_description, crate_name, crate_version};
+use clap::parser::ValueSource;
+use clap::{
+    Arg, ArgAction, ArgMatches, Command, crate_authors, crate_description, crate_name,
+    crate_version,
+};
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use policy_evaluator::burrego;
@@ -20,8 +27,18 @@ lazy_static! {
     };
 }
 
+/// The flags that cannot be set inside of the configuration file
+const FLAGS_NOT_IN_CONFIG_FILE: &[&str] = &["config", "help", "version"];
+
 pub(crate) fn build_cli() -> Command {
     let mut args = vec![
+        Arg::new("config")
+            .long("config")
+            .value_name("CONFIG_FILE")
+            .env("KUBEWARDEN_CONFIG")
+            .value_parser(clap::builder::PathBufValueParser::new())
+            .help("YAML file holding the values of the flags, indexed by their name. The flags provided via the command line or the environment take precedence over the file"),
+
         Arg::new("log-level")
             .long("log-level")
             .value_name("LOG_LEVEL")
@@ -75,6 +92,32 @@ pub(crate) fn build_cli() -> Command {
             .env("KUBEWARDEN_READINESS_PROBE_PORT")
             .help("Expose readiness endpoint on READINESS_PROBE_PORT"),
 
+        Arg::new("readiness-max-policy-errors")
+            .long("readiness-max-policy-errors")
+            .value_name("MAX_POLICY_ERRORS")
+            .env("KUBEWARDEN_READINESS_MAX_POLICY_ERRORS")
+            .required(false)
+            .help("Report the server as not ready when more than MAX_POLICY_ERRORS policies failed to initialize"),
+
+        Arg::new("readiness-fail-on-reload")
+            .long("readiness-fail-on-reload")
+            .env("KUBEWARDEN_READINESS_FAIL_ON_RELOAD")
+            .action(ArgAction::SetTrue)
+            .help("Report the server as not ready while the policies are being reloaded"),
+
+        Arg::new("redact-error-details")
+            .long("redact-error-details")
+            .env("KUBEWARDEN_REDACT_ERROR_DETAILS")
+            .action(ArgAction::SetTrue)
+            .help("Do not report the details of the evaluation errors and failures inside of the responses, only their code"),
+
+        Arg::new("evaluation-cache-size")
+            .long("evaluation-cache-size")
+            .value_name("ENTRIES")
+            .env("KUBEWARDEN_EVALUATION_CACHE_SIZE")
+            .required(false)
+            .help("Cache up to ENTRIES responses of the policies, identical requests are not evaluated again. The cache is disabled by default"),
+
         Arg::new("workers")
             .long("workers")
             .value_name("WORKERS_NUMBER")
@@ -105,10 +148,40 @@ pub(crate) fn build_cli() -> Command {
 
         Arg::new("policies")
             .long("policies")
+            .action(ArgAction::Append)
+            .value_delimiter(',')
             .value_name("POLICIES_FILE")
             .env("KUBEWARDEN_POLICIES")
             .default_value("policies.yml")
-            .help("YAML file holding the policies to be loaded and their settings"),
+            .help("YAML file holding the policies to be loaded and their settings, or a directory containing such files. Can be repeated, the policies are merged together"),
+
+        Arg::new("enable-policies-hot-reload")
+            .long("enable-policies-hot-reload")
+            .env("KUBEWARDEN_ENABLE_POLICIES_HOT_RELOAD")
+            .action(ArgAction::SetTrue)
+            .help("Watch the policies file and reload the policies when it changes, without restarting the process"),
+
+        Arg::new("enable-settings-interpolation")
+            .long("enable-settings-interpolation")
+            .env("KUBEWARDEN_ENABLE_SETTINGS_INTERPOLATION")
+            .action(ArgAction::SetTrue)
+            .help("Resolve the references to environment variables and files found inside of the settings of the policies. Only the allowed environment variables and files can be referenced"),
+
+        Arg::new("settings-interpolation-allowed-dirs")
+            .long("settings-interpolation-allowed-dirs")
+            .action(ArgAction::Append)
+            .value_delimiter(',')
+            .value_name("DIR")
+            .env("KUBEWARDEN_SETTINGS_INTERPOLATION_ALLOWED_DIRS")
+            .help("Directories holding the files that can be referenced inside of the settings of the policies"),
+
+        Arg::new("settings-interpolation-allowed-env-prefixes")
+            .long("settings-interpolation-allowed-env-prefixes")
+            .action(ArgAction::Append)
+            .value_delimiter(',')
+            .value_name("PREFIX")
+            .env("KUBEWARDEN_SETTINGS_INTERPOLATION_ALLOWED_ENV_PREFIXES")
+            .help("Prefixes of the names of the environment variables that can be referenced inside of the settings of the policies"),
 
         Arg::new("policies-download-dir")
             .long("policies-download-dir")
@@ -150,10 +223,19 @@ pub(crate) fn build_cli() -> Command {
 
         Arg::new("always-accept-admission-reviews-on-namespace")
             .long("always-accept-admission-reviews-on-namespace")
+            .action(ArgAction::Append)
+            .value_delimiter(',')
             .value_name("NAMESPACE")
             .env("KUBEWARDEN_ALWAYS_ACCEPT_ADMISSION_REVIEWS_ON_NAMESPACE")
             .required(false)
-            .help("Always accept AdmissionReviews that target the given namespace"),
+            .help("Always accept AdmissionReviews that target the given namespace. Glob patterns like `kube-*` are supported. Can be repeated"),
+
+        Arg::new("always-accept-admission-reviews-on-namespace-selector")
+            .long("always-accept-admission-reviews-on-namespace-selector")
+            .value_name("LABEL_SELECTOR")
+            .env("KUBEWARDEN_ALWAYS_ACCEPT_ADMISSION_REVIEWS_ON_NAMESPACE_SELECTOR")
+            .required(false)
+            .help("Always accept AdmissionReviews that target the namespaces matching the given label selector. The namespaces are looked up periodically"),
 
         Arg::new("disable-timeout-protection")
             .long("disable-timeout-protection")
@@ -212,6 +294,8 @@ pub(crate) fn build_cli() -> Command {
     ];
 
     args.sort_by(|a, b| a.get_id().cmp(b.get_id()));
+    // The subcommands evaluating policies rely on the same flags as the server
+    let args = args.into_iter().map(|arg| arg.global(true));
 
     Command::new(crate_name!())
         .author(crate_authors!())
@@ -231,4 +315,247 @@ pub(crate) fn build_cli() -> Command {
                         .help("path where the documentation file will be stored"),
                 ),
         )
+        .subcommand(
+            Command::new("check-config")
+                .about("Loads the policies and validates their settings, without starting the server. Exits with 1 when a policy is not valid"),
+        )
+        .subcommand(
+            Command::new("schema")
+                .about("Prints the JSON Schema of the policies file"),
+        )
+        .subcommand(
+            Command::new("show-config")
+                .about("Prints the effective configuration, merging the configuration file with the flags provided via the command line and the environment"),
+        )
+        .subcommand(
+            Command::new("test")
+                .about("Evaluates a request against the policies, without starting the server. Exits with 1 when the request is rejected")
+                .arg(
+                    Arg::new("request")
+                        .long("request")
+                        .required_unless_present("suite")
+                        .conflicts_with("suite")
+                        .value_name("REQUEST_FILE")
+                        .value_parser(clap::builder::PathBufValueParser::new())
+                        .help("JSON file holding the AdmissionReview to evaluate"),
+                )
+                .arg(
+                    Arg::new("policy")
+                        .long("policy")
+                        .action(ArgAction::Append)
+                        .conflicts_with("suite")
+                        .value_name("POLICY_ID")
+                        .help("ID of the policy to evaluate, can be repeated. All the policies are evaluated when not provided"),
+                )
+                .arg(
+                    Arg::new("suite")
+                        .long("suite")
+                        .value_name("SUITE_FILE")
+                        .value_parser(clap::builder::PathBufValueParser::new())
+                        .help("YAML file holding a suite of test cases to run. Exits with 1 when a test case fails"),
+                )
+                .arg(
+                    Arg::new("output-format")
+                        .long("output-format")
+                        .value_name("FORMAT")
+                        .default_value("tap")
+                        .requires("suite")
+                        .value_parser([PossibleValue::new("tap"), PossibleValue::new("junit")])
+                        .help("Format of the report of the test suite"),
+                ),
+        )
+}
+
+/// Returns the matches of the innermost subcommand, the global flags are propagated to it
+fn leaf_matches(matches: &ArgMatches) -> &ArgMatches {
+    match matches.subcommand() {
+        Some((_, sub_matches)) => leaf_matches(sub_matches),
+        None => matches,
+    }
+}
+
+/// Whether the flag accepts a list of values
+fn is_multi_valued(arg: &Arg) -> bool {
+    matches!(arg.get_action(), ArgAction::Append) || arg.get_value_delimiter().is_some()
+}
+
+/// Returns the command line arguments that set the values defined inside of the
+/// configuration file provided via `--config`. The flags already provided via the
+/// command line or via the environment are left out, these take precedence over
+/// the file.
+///
+/// The configuration file is a YAML dictionary, the keys are the names of the flags.
+/// No arguments are returned when no configuration file is provided.
+pub(crate) fn config_file_args(matches: &ArgMatches) -> Result<Vec<String>> {
+    let matches = leaf_matches(matches);
+    let Some(config_file) = matches.get_one::<PathBuf>("config") else {
+        return Ok(Vec::new());
+    };
+
+    let file = File::open(config_file)
+        .map_err(|e| anyhow!("cannot open configuration file {config_file:?}: {e}"))?;
+    let values: BTreeMap<String, serde_yaml::Value> = serde_yaml::from_reader(file)
+        .map_err(|e| anyhow!("cannot parse configuration file {config_file:?}: {e}"))?;
+
+    let cli = build_cli();
+    let mut args = Vec::new();
+    for (key, value) in values {
+        let error = |message: &str| anyhow!("configuration file {config_file:?}: {key}: {message}");
+        let arg = cli
+            .get_arguments()
+            .find(|arg| arg.get_id() == key.as_str())
+            .filter(|_| !FLAGS_NOT_IN_CONFIG_FILE.contains(&key.as_str()))
+            .ok_or_else(|| error("unknown flag"))?;
+
+        if matches!(
+            matches.value_source(&key),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+
+        let long = arg.get_long().expect("all the flags have a long name");
+        if matches!(arg.get_action(), ArgAction::SetTrue) {
+            match value {
+                serde_yaml::Value::Bool(true) => args.push(format!("--{long}")),
+                serde_yaml::Value::Bool(false) => {}
+                _ => return Err(error("expected a boolean")),
+            }
+            continue;
+        }
+
+        let values = match value {
+            serde_yaml::Value::Sequence(values) if is_multi_valued(arg) => values,
+            serde_yaml::Value::Sequence(_) => return Err(error("expected a single value")),
+            value => vec![value],
+        };
+        let values = values
+            .into_iter()
+            .map(|value| match value {
+                serde_yaml::Value::String(value) => Ok(value),
+                serde_yaml::Value::Number(value) => Ok(value.to_string()),
+                serde_yaml::Value::Bool(value) => Ok(value.to_string()),
+                _ => Err(error("expected a string, a number or a boolean")),
+            })
+            .collect::<Result<Vec<String>>>()?;
+
+        match arg.get_value_delimiter() {
+            Some(delimiter) if !values.is_empty() => {
+                args.push(format!("--{long}={}", values.join(&delimiter.to_string())));
+            }
+            _ => args.extend(values.iter().map(|value| format!("--{long}={value}"))),
+        }
+    }
+
+    Ok(args)
+}
+
+/// Renders the value of all the flags as a YAML document. The document can be used
+/// as configuration file.
+pub(crate) fn effective_config(matches: &ArgMatches) -> Result<String> {
+    let matches = leaf_matches(matches);
+
+    let mut config = BTreeMap::new();
+    for arg in build_cli().get_arguments() {
+        let id = arg.get_id().as_str();
+        if FLAGS_NOT_IN_CONFIG_FILE.contains(&id) {
+            continue;
+        }
+
+        let value = if matches!(arg.get_action(), ArgAction::SetTrue) {
+            serde_yaml::Value::Bool(matches.get_flag(id))
+        } else {
+            let Some(values) = matches.get_raw(id) else {
+                continue;
+            };
+            let mut values: Vec<serde_yaml::Value> = values
+                .map(|value| serde_yaml::Value::String(value.to_string_lossy().into_owned()))
+                .collect();
+            if is_multi_valued(arg) {
+                serde_yaml::Value::Sequence(values)
+            } else {
+                values.pop().unwrap_or_default()
+            }
+        };
+        config.insert(id.to_owned(), value);
+    }
+
+    Ok(serde_yaml::to_string(&config)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn matches_with_config_file(config_yaml: &str, extra_args: &[&str]) -> Result<ArgMatches> {
+        let mut config_file = NamedTempFile::new().unwrap();
+        config_file.write_all(config_yaml.as_bytes()).unwrap();
+        let config_file = config_file.into_temp_path();
+
+        let mut args = vec![
+            "policy-server".to_owned(),
+            format!("--config={}", config_file.display()),
+        ];
+        args.extend(extra_args.iter().map(|arg| arg.to_string()));
+
+        let matches = build_cli().try_get_matches_from(&args).unwrap();
+        args.extend(config_file_args(&matches)?);
+        Ok(build_cli().try_get_matches_from(&args).unwrap())
+    }
+
+    #[test]
+    fn command_line_flags_take_precedence_over_the_config_file() {
+        let config_yaml = r#"
+port: 8443
+log-level: debug
+enable-metrics: true
+policies:
+  - /policies/team-a
+  - /policies/team-b
+"#;
+
+        let matches = matches_with_config_file(config_yaml, &["--port", "3000"]).unwrap();
+
+        assert_eq!(matches.get_one::<String>("port").unwrap(), "3000");
+        assert_eq!(matches.get_one::<String>("log-level").unwrap(), "debug");
+        assert!(matches.get_flag("enable-metrics"));
+        assert_eq!(
+            matches
+                .get_many::<String>("policies")
+                .unwrap()
+                .collect::<Vec<_>>(),
+            vec!["/policies/team-a", "/policies/team-b"]
+        );
+    }
+
+    #[test]
+    fn config_file_rejects_unknown_flags() {
+        let error = matches_with_config_file("prot: 8443\n", &[])
+            .unwrap_err()
+            .to_string();
+
+        assert!(
+            error.contains("prot: unknown flag"),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test]
+    fn effective_config_can_be_used_as_config_file() {
+        let matches = matches_with_config_file("workers: 4\n", &["--enable-pprof"]).unwrap();
+
+        let config_yaml = effective_config(&matches).unwrap();
+        let config: BTreeMap<String, serde_yaml::Value> =
+            serde_yaml::from_str(&config_yaml).unwrap();
+
+        assert_eq!(config["workers"], serde_yaml::Value::from("4"));
+        assert_eq!(config["enable-pprof"], serde_yaml::Value::from(true));
+        assert_eq!(config["port"], serde_yaml::Value::from("3000"));
+        assert!(!config.contains_key("config"));
+
+        let matches = matches_with_config_file(&config_yaml, &[]).unwrap();
+        assert_eq!(effective_config(&matches).unwrap(), config_yaml);
+    }
 }
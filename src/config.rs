@@ -11,15 +11,23 @@ use policy_evaluator::{
     },
     policy_metadata::ContextAwareResource,
 };
-use serde::Deserialize;
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema, schema_for};
+use serde::{Deserialize, Deserializer, de};
+use serde_yaml::value::{Tag, TaggedValue};
 use std::{
     collections::{BTreeSet, HashMap},
-    env,
+    env, fmt,
     fs::{self, File},
     net::SocketAddr,
+    num::{NonZeroU64, NonZeroUsize},
     path::{Path, PathBuf},
 };
 
+mod settings_interpolation;
+
+pub use settings_interpolation::SettingsInterpolation;
+use settings_interpolation::interpolate_settings;
+
 pub static SERVICE_NAME: &str = "kubewarden-policy-server";
 const DOCKER_CONFIG_ENV_VAR: &str = "DOCKER_CONFIG";
 
@@ -33,9 +41,16 @@ pub struct Config {
     pub readiness_probe_addr: SocketAddr,
     pub sources: Option<Sources>,
     pub policies: HashMap<String, PolicyOrPolicyGroup>,
+    // The files and the directories the policies have been read from. This is empty
+    // when the policies have not been loaded from the filesystem.
+    pub policies_paths: Vec<PathBuf>,
+    pub enable_policies_hot_reload: bool,
     pub policies_download_dir: PathBuf,
     pub ignore_kubernetes_connection_failure: bool,
-    pub always_accept_admission_reviews_on_namespace: Option<String>,
+    /// Names and glob patterns of the Namespaces where all the requests are accepted
+    pub always_accept_admission_reviews_on_namespaces: Vec<String>,
+    /// Label selector of the Namespaces where all the requests are accepted
+    pub always_accept_admission_reviews_on_namespace_selector: Option<String>,
     // This is the global timeout for each policy evaluation.
     pub policy_evaluation_limit_seconds: Option<u64>,
     pub tls_config: Option<TlsConfig>,
@@ -52,6 +67,19 @@ pub struct Config {
     pub daemon_stdout_file: Option<String>,
     pub daemon_stderr_file: Option<String>,
     pub continue_on_errors: bool,
+    /// Number of policies that can fail to initialize before the readiness probe
+    /// reports the Policy Server as not ready
+    pub readiness_max_policy_errors: Option<usize>,
+    /// Report the Policy Server as not ready while the policies are being reloaded
+    pub readiness_fail_on_reload: bool,
+    /// Do not report the details of the evaluation errors inside of the responses
+    pub redact_error_details: bool,
+    /// Number of responses kept inside of the evaluation cache, the cache is disabled
+    /// when not set
+    pub evaluation_cache_size: Option<NonZeroUsize>,
+    /// The references that can be resolved inside of the settings of the policies, the
+    /// settings are not interpolated when not set
+    pub settings_interpolation: Option<SettingsInterpolation>,
 }
 
 pub struct TlsConfig {
@@ -66,7 +94,18 @@ impl Config {
         let addr = api_bind_address(matches)?;
         let readiness_probe_addr = readiness_probe_bind_address(matches)?;
 
-        let policies = policies(matches)?;
+        let policies_paths: Vec<PathBuf> = matches
+            .get_many::<String>("policies")
+            .expect("This should not happen, there's a default value for policies")
+            .map(PathBuf::from)
+            .collect();
+        let settings_interpolation = settings_interpolation(matches);
+        let policies =
+            read_and_validate_policies(&policies_paths, settings_interpolation.as_ref())?;
+        let enable_policies_hot_reload = matches
+            .get_one::<bool>("enable-policies-hot-reload")
+            .expect("clap should have set a default value")
+            .to_owned();
         let policies_download_dir = matches
             .get_one::<String>("policies-download-dir")
             .map(PathBuf::from)
@@ -91,9 +130,14 @@ impl Config {
                 v.parse::<usize>()
                     .expect("error parsing the number of workers")
             });
-        let always_accept_admission_reviews_on_namespace = matches
-            .get_one::<String>("always-accept-admission-reviews-on-namespace")
-            .map(|s| s.to_owned());
+        let always_accept_admission_reviews_on_namespaces = matches
+            .get_many::<String>("always-accept-admission-reviews-on-namespace")
+            .unwrap_or_default()
+            .cloned()
+            .collect();
+        let always_accept_admission_reviews_on_namespace_selector = matches
+            .get_one::<String>("always-accept-admission-reviews-on-namespace-selector")
+            .cloned();
 
         let metrics_enabled = matches
             .get_one::<bool>("enable-metrics")
@@ -145,15 +189,41 @@ impl Config {
             .expect("clap should have assigned a default value")
             .to_owned();
 
+        let readiness_max_policy_errors = matches
+            .get_one::<String>("readiness-max-policy-errors")
+            .map(|v| {
+                v.parse::<usize>()
+                    .map_err(|e| anyhow!("error parsing readiness-max-policy-errors: {e}"))
+            })
+            .transpose()?;
+        let readiness_fail_on_reload = matches
+            .get_one::<bool>("readiness-fail-on-reload")
+            .expect("clap should have assigned a default value")
+            .to_owned();
+        let redact_error_details = matches
+            .get_one::<bool>("redact-error-details")
+            .expect("clap should have assigned a default value")
+            .to_owned();
+        let evaluation_cache_size = matches
+            .get_one::<String>("evaluation-cache-size")
+            .map(|v| {
+                v.parse::<NonZeroUsize>()
+                    .map_err(|e| anyhow!("error parsing evaluation-cache-size: {e}"))
+            })
+            .transpose()?;
+
         Ok(Self {
             addr,
             readiness_probe_addr,
             sources,
             policies,
+            policies_paths,
+            enable_policies_hot_reload,
             policies_download_dir,
             ignore_kubernetes_connection_failure,
             tls_config,
-            always_accept_admission_reviews_on_namespace,
+            always_accept_admission_reviews_on_namespaces,
+            always_accept_admission_reviews_on_namespace_selector,
             policy_evaluation_limit_seconds,
             pool_size,
             metrics_enabled,
@@ -168,10 +238,37 @@ impl Config {
             daemon_stderr_file,
             enable_pprof,
             continue_on_errors,
+            readiness_max_policy_errors,
+            readiness_fail_on_reload,
+            redact_error_details,
+            evaluation_cache_size,
+            settings_interpolation,
         })
     }
 }
 
+fn settings_interpolation(matches: &clap::ArgMatches) -> Option<SettingsInterpolation> {
+    if !matches
+        .get_one::<bool>("enable-settings-interpolation")
+        .expect("clap should have set a default value")
+    {
+        return None;
+    }
+
+    Some(SettingsInterpolation {
+        allowed_dirs: matches
+            .get_many::<String>("settings-interpolation-allowed-dirs")
+            .unwrap_or_default()
+            .map(PathBuf::from)
+            .collect(),
+        allowed_env_prefixes: matches
+            .get_many::<String>("settings-interpolation-allowed-env-prefixes")
+            .unwrap_or_default()
+            .cloned()
+            .collect(),
+    })
+}
+
 fn api_bind_address(matches: &clap::ArgMatches) -> Result<SocketAddr> {
     format!(
         "{}:{}",
@@ -219,30 +316,102 @@ fn build_tls_config(matches: &clap::ArgMatches) -> Result<Option<TlsConfig>> {
     }
 }
 
-fn policies(matches: &clap::ArgMatches) -> Result<HashMap<String, PolicyOrPolicyGroup>> {
-    let policies_file = Path::new(matches.get_one::<String>("policies").unwrap());
-    let policies = read_policies_file(policies_file).map_err(|e| {
-        anyhow!(
-            "error while loading policies from {:?}: {}",
-            policies_file,
-            e
-        )
-    })?;
+/// Read the policies defined inside of the given files and validate them.
+/// When a path is a directory, all the YAML files it contains are read.
+///
+/// The policies of all the files are merged together, defining the same policy
+/// inside of two files is an error.
+///
+/// The settings are interpolated only when `settings_interpolation` is set.
+pub(crate) fn read_and_validate_policies(
+    policies_paths: &[PathBuf],
+    settings_interpolation: Option<&SettingsInterpolation>,
+) -> Result<HashMap<String, PolicyOrPolicyGroup>> {
+    let mut policies = HashMap::new();
+    let mut policy_sources: HashMap<String, PathBuf> = HashMap::new();
+
+    for policies_file in policies_files(policies_paths)? {
+        let file_policies =
+            read_policies_file(&policies_file, settings_interpolation).map_err(|e| {
+                anyhow!(
+                    "error while loading policies from {:?}: {}",
+                    policies_file,
+                    e
+                )
+            })?;
+
+        for (name, policy) in file_policies {
+            if let Some(source) = policy_sources.get(&name) {
+                return Err(anyhow!(
+                    "policy '{}' is defined both inside of {:?} and {:?}",
+                    name,
+                    source,
+                    policies_file
+                ));
+            }
+            policy_sources.insert(name.clone(), policies_file.clone());
+            policies.insert(name, policy);
+        }
+    }
 
     validate_policies(&policies)?;
 
     Ok(policies)
 }
 
+/// Returns the files holding the policies. The directories are expanded into the
+/// YAML files they contain, sorted by name. Hidden files are ignored, this skips
+/// the internal files of the ConfigMaps mounted by Kubernetes.
+fn policies_files(policies_paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for path in policies_paths {
+        if !path.is_dir() {
+            files.push(path.to_owned());
+            continue;
+        }
+
+        let mut dir_files = Vec::new();
+        for entry in fs::read_dir(path)
+            .map_err(|e| anyhow!("cannot read policies directory {:?}: {}", path, e))?
+        {
+            let entry_path = entry
+                .map_err(|e| anyhow!("cannot read policies directory {:?}: {}", path, e))?
+                .path();
+            let hidden = entry_path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            let yaml = entry_path
+                .extension()
+                .is_some_and(|extension| extension == "yml" || extension == "yaml");
+            if entry_path.is_file() && yaml && !hidden {
+                dir_files.push(entry_path);
+            }
+        }
+        dir_files.sort();
+        files.extend(dir_files);
+    }
+
+    Ok(files)
+}
+
 // Validate the policies and policy groups:
 //  - ensure policy names do not contain a '/' character
 //  - ensure names of policy group's policies do not contain a '/' character
+//  - ensure the mutators of a policy group are members of the group, and that
+//    the group is allowed to mutate
 fn validate_policies(policies: &HashMap<String, PolicyOrPolicyGroup>) -> Result<()> {
     for (name, policy) in policies.iter() {
         if name.contains('/') {
             return Err(anyhow!("policy name '{}' contains a '/' character", name));
         }
-        if let PolicyOrPolicyGroup::PolicyGroup { policies, .. } = policy {
+        if let PolicyOrPolicyGroup::PolicyGroup {
+            policies,
+            allowed_to_mutate,
+            mutators,
+            ..
+        } = policy
+        {
             let policies_with_invalid_name: Vec<String> = policies
                 .iter()
                 .filter_map(|(id, _)| if id.contains('/') { Some(id) } else { None })
@@ -255,6 +424,24 @@ fn validate_policies(policies: &HashMap<String, PolicyOrPolicyGroup>) -> Result<
                     policies_with_invalid_name
                 ));
             }
+
+            let unknown_mutators: Vec<&String> = mutators
+                .iter()
+                .filter(|mutator| !policies.contains_key(*mutator))
+                .collect();
+            if !unknown_mutators.is_empty() {
+                return Err(anyhow!(
+                    "policy group '{}' has mutators that are not part of the group: {:?}",
+                    name,
+                    unknown_mutators
+                ));
+            }
+            if !mutators.is_empty() && !allowed_to_mutate.unwrap_or(false) {
+                return Err(anyhow!(
+                    "policy group '{}' has mutators but it is not allowed to mutate",
+                    name
+                ));
+            }
         }
     }
     Ok(())
@@ -294,24 +481,105 @@ pub enum PolicyOrPolicyGroupSettings {
     Policy(PolicySettings),
     PolicyGroup {
         expression: String,
+        expression_language: ExpressionLanguage,
+        members_evaluation: MembersEvaluation,
+        mutators: Vec<String>,
+        trace: bool,
         message: String,
         policies: Vec<String>,
     },
 }
 
+/// The language used to write the expression of a policy group
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpressionLanguage {
+    /// Rhai, the members of the group are exposed as functions
+    #[default]
+    Rhai,
+    /// Common Expression Language, the members of the group are exposed as variables
+    /// and the admission request as `request`
+    Cel,
+}
+
+/// How the members of a policy group are evaluated
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MembersEvaluation {
+    /// The members are evaluated one after the other, when the expression needs them
+    #[default]
+    Sequential,
+    /// The members are evaluated concurrently, the expression waits only for the
+    /// results it needs
+    Parallel,
+}
+
+/// What to do when the evaluation of a policy fails, because of a trap of the Wasm module,
+/// a timeout or an initialization error. The values match the ones of the `failurePolicy`
+/// of the Kubernetes webhooks.
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+pub enum FailurePolicy {
+    /// The request is accepted, with a warning reporting the failure
+    Ignore,
+    /// The request is rejected, with a message reporting the failure
+    Fail,
+}
+
+impl fmt::Display for FailurePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FailurePolicy::Ignore => write!(f, "Ignore"),
+            FailurePolicy::Fail => write!(f, "Fail"),
+        }
+    }
+}
+
 /// `PolicyGroupMember` represents a single policy that is part of a policy group.
-#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub struct PolicyGroupMember {
     /// The URL where the policy is located
     pub module: String,
     /// The settings for the policy
+    #[schemars(with = "Option<serde_json::Map<String, serde_json::Value>>")]
     pub settings: Option<PolicySettings>,
     /// The list of Kubernetes resources the policy is allowed to access
     #[serde(default)]
+    #[schemars(schema_with = "context_aware_resources_schema")]
     pub context_aware_resources: BTreeSet<ContextAwareResource>,
     /// Timeout for the evaluation of the policy
     pub timeout_eval_seconds: Option<u64>,
+    /// The mode of the policy. The rejections of a policy in monitor mode are logged,
+    /// but the policy is considered as accepting the request by the group expression
+    #[serde(default)]
+    #[schemars(schema_with = "policy_mode_schema")]
+    pub policy_mode: PolicyMode,
+    /// How the group expression treats the policy when its evaluation times out
+    #[serde(default)]
+    pub on_timeout: OnTimeout,
+}
+
+/// How a policy group treats a member whose evaluation times out
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OnTimeout {
+    /// The member is considered as accepting the request
+    Allow,
+    /// The member is considered as rejecting the request
+    Deny,
+    /// The evaluation of the whole group fails
+    #[default]
+    Error,
+}
+
+impl fmt::Display for OnTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OnTimeout::Allow => write!(f, "allow"),
+            OnTimeout::Deny => write!(f, "deny"),
+            OnTimeout::Error => write!(f, "error"),
+        }
+    }
 }
 
 impl PolicyGroupMember {
@@ -322,47 +590,230 @@ impl PolicyGroupMember {
     }
 }
 
+/// Conditions an admission request must satisfy to be evaluated by a policy. The requests
+/// that do not satisfy them are accepted without evaluating the policy.
+///
+/// All the conditions must be satisfied. The empty lists are satisfied by any request.
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct MatchConditions {
+    /// The operations of the request, like `CREATE` or `UPDATE`
+    #[serde(default)]
+    pub operations: BTreeSet<String>,
+    /// The kinds of the resources, like `Pod` or `Deployment`
+    #[serde(default)]
+    pub kinds: BTreeSet<String>,
+    /// The namespaces of the resources
+    #[serde(default)]
+    pub namespaces: BTreeSet<String>,
+    /// The names of the users making the request
+    #[serde(default)]
+    pub users: BTreeSet<String>,
+    /// The groups of the user making the request, one of them is enough
+    #[serde(default)]
+    pub groups: BTreeSet<String>,
+    /// A boolean Rhai expression. The admission request is available as `request`
+    pub expression: Option<String>,
+}
+
 /// Describes a policy that can be either an individual policy or a group policy.
-#[derive(Deserialize, Debug, Clone, PartialEq)]
-#[serde(untagged)]
+///
+/// Inside of the policies file, policies are told apart from policy groups by
+/// the presence of the `module` field.
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(remote = "Self", rename_all_fields = "camelCase")]
+#[schemars(!remote, untagged, rename = "PolicyOrPolicyGroup")]
 pub enum PolicyOrPolicyGroup {
     /// An individual policy
-    #[serde(rename_all = "camelCase")]
     Policy {
         /// The URL where the policy is located
         module: String,
-        #[serde(default)]
         /// The mode of the policy
+        #[serde(default)]
+        #[schemars(schema_with = "policy_mode_schema")]
         policy_mode: PolicyMode,
         /// Whether the policy is allowed to mutate the request
         allowed_to_mutate: Option<bool>,
         /// The settings for the policy, as provided by the user
+        #[schemars(with = "Option<serde_json::Map<String, serde_json::Value>>")]
         settings: Option<PolicySettings>,
-        #[serde(default)]
         /// The list of Kubernetes resources the policy is allowed to access
+        #[serde(default)]
+        #[schemars(schema_with = "context_aware_resources_schema")]
         context_aware_resources: BTreeSet<ContextAwareResource>,
         /// The message that is returned when the policy evaluates to false
         message: Option<String>,
         /// Timeout for the evaluation of the policy
         timeout_eval_seconds: Option<u64>,
+        /// What to do when the evaluation of the policy fails: `Ignore` accepts the request,
+        /// `Fail` rejects it. When not set, the error is returned to the API server, which
+        /// applies the `failurePolicy` of the webhook
+        failure_policy: Option<FailurePolicy>,
+        /// Maximum number of evaluations running at the same time. The requests exceeding
+        /// the limit wait for a running evaluation to complete, the concurrency is not limited
+        /// when not set
+        max_concurrency: Option<NonZeroUsize>,
+        /// Cache the responses of the policy for the given number of seconds, when the
+        /// evaluation cache is enabled. Only the policies whose responses depend just on the
+        /// request and on their settings should opt in: not the ones with side effects, nor
+        /// the ones looking up external state like signatures or DNS records. The responses
+        /// of the context-aware policies are never cached
+        cache_ttl_seconds: Option<NonZeroU64>,
+        /// The conditions a request must satisfy to be evaluated by the policy
+        match_conditions: Option<MatchConditions>,
     },
     /// A group of policies that are evaluated together using a given expression
-    #[serde(rename_all = "camelCase")]
     PolicyGroup {
         /// The mode of the policy
         #[serde(default)]
+        #[schemars(schema_with = "policy_mode_schema")]
         policy_mode: PolicyMode,
-        /// The policies that make up for this group
-        /// Key is a unique identifier
+        /// The policies that make up for this group, indexed by a unique identifier
         policies: HashMap<String, PolicyGroupMember>,
         /// The expression that is used to evaluate the group of policies
         expression: String,
+        /// The language of the expression, `rhai` by default
+        #[serde(default)]
+        expression_language: ExpressionLanguage,
+        /// How the members of the group are evaluated, `sequential` by default
+        #[serde(default)]
+        members_evaluation: MembersEvaluation,
+        /// Whether the group is allowed to mutate the request
+        allowed_to_mutate: Option<bool>,
+        /// The members allowed to mutate the request. They are evaluated in this order, before
+        /// the expression, each one receiving the object mutated by the previous ones
+        #[serde(default)]
+        mutators: Vec<String>,
+        /// Report the results of the members inside of the audit annotations of the responses.
+        /// The responses to the audit scanner always include them
+        #[serde(default)]
+        trace: bool,
         /// The message that is returned when the group of policies evaluates to false
         message: String,
+        /// What to do when the evaluation of the group of policies fails: `Ignore` accepts the
+        /// request, `Fail` rejects it. When not set, the error is returned to the API server,
+        /// which applies the `failurePolicy` of the webhook
+        failure_policy: Option<FailurePolicy>,
+        /// Maximum number of evaluations running at the same time. The requests exceeding
+        /// the limit wait for a running evaluation to complete, the concurrency is not limited
+        /// when not set
+        max_concurrency: Option<NonZeroUsize>,
+        /// The conditions a request must satisfy to be evaluated by the group of policies
+        match_conditions: Option<MatchConditions>,
     },
 }
 
+/// Error raised when the definition of a policy is not valid
+#[derive(Debug)]
+struct PolicyDefinitionError {
+    /// The path of the invalid field, "." when the whole definition is invalid
+    path: String,
+    message: String,
+}
+
+impl PolicyDefinitionError {
+    /// Describe the error, the path of the invalid field is prefixed with `prefix`
+    fn qualified(&self, prefix: &str) -> String {
+        if self.path == "." {
+            format!("{prefix}: {}", self.message)
+        } else {
+            format!("{prefix}.{}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl fmt::Display for PolicyDefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path == "." {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PolicyOrPolicyGroup {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let definition = serde_yaml::Value::deserialize(deserializer)?;
+        PolicyOrPolicyGroup::from_definition(definition).map_err(de::Error::custom)
+    }
+}
+
+fn policy_mode_schema(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "type": "string",
+        "enum": ["monitor", "protect"],
+    })
+}
+
+fn context_aware_resources_schema(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "type": "array",
+        "uniqueItems": true,
+        "items": {
+            "type": "object",
+            "properties": {
+                "apiVersion": { "type": "string" },
+                "kind": { "type": "string" },
+            },
+            "required": ["apiVersion", "kind"],
+        },
+    })
+}
+
+/// Returns the JSON Schema of the policies file
+pub fn policies_json_schema() -> Schema {
+    schema_for!(HashMap<String, PolicyOrPolicyGroup>)
+}
+
 impl PolicyOrPolicyGroup {
+    /// Build a policy or a policy group from its definition. The errors report the
+    /// path of the field that is not valid.
+    fn from_definition(
+        definition: serde_yaml::Value,
+    ) -> std::result::Result<Self, PolicyDefinitionError> {
+        let is_policy = definition
+            .as_mapping()
+            .ok_or_else(|| PolicyDefinitionError {
+                path: ".".to_owned(),
+                message: "expected a policy or a policy group definition".to_owned(),
+            })?
+            .contains_key("module");
+
+        // The derived implementation expects the variant as a YAML tag, the definitions
+        // don't have one
+        let variant = if is_policy { "Policy" } else { "PolicyGroup" };
+        let definition = serde_yaml::Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new(variant),
+            value: definition,
+        }));
+
+        let mut track = serde_path_to_error::Track::new();
+        PolicyOrPolicyGroup::deserialize(serde_path_to_error::Deserializer::new(
+            definition, &mut track,
+        ))
+        .map_err(|error| {
+            // The first segment of the path is the variant, which is not part of the file
+            let path = track
+                .path()
+                .iter()
+                .skip(1)
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            PolicyDefinitionError {
+                path: if path.is_empty() {
+                    ".".to_owned()
+                } else {
+                    path.join(".")
+                },
+                message: error.to_string(),
+            }
+        })
+    }
+
     pub fn settings(&self) -> Result<PolicyOrPolicyGroupSettings> {
         match self {
             PolicyOrPolicyGroup::Policy { settings, .. } => Ok(
@@ -370,11 +821,19 @@ impl PolicyOrPolicyGroup {
             ),
             PolicyOrPolicyGroup::PolicyGroup {
                 expression,
+                expression_language,
+                members_evaluation,
+                mutators,
+                trace,
                 message,
                 policies,
                 ..
             } => Ok(PolicyOrPolicyGroupSettings::PolicyGroup {
                 expression: expression.clone(),
+                expression_language: *expression_language,
+                members_evaluation: *members_evaluation,
+                mutators: mutators.clone(),
+                trace: *trace,
                 message: message.clone(),
                 policies: policies.keys().cloned().collect(),
             }),
@@ -386,10 +845,29 @@ impl PolicyOrPolicyGroup {
 /// and Policy as values. The key is the name of the policy as provided by the user
 /// inside of the configuration file. This name is used to build the API path
 /// exposing the policy.
-fn read_policies_file(path: &Path) -> Result<HashMap<String, PolicyOrPolicyGroup>> {
+///
+/// When `settings_interpolation` is set, the references to environment variables and
+/// files found inside of the settings are resolved while reading the file, see
+/// `interpolate_settings`.
+fn read_policies_file(
+    path: &Path,
+    settings_interpolation: Option<&SettingsInterpolation>,
+) -> Result<HashMap<String, PolicyOrPolicyGroup>> {
     let settings_file = File::open(path)?;
-    let ps: HashMap<String, PolicyOrPolicyGroup> = serde_yaml::from_reader(&settings_file)?;
-    Ok(ps)
+    let definitions: HashMap<String, serde_yaml::Value> = serde_yaml::from_reader(&settings_file)?;
+
+    definitions
+        .into_iter()
+        .map(|(name, mut definition)| {
+            let policy = settings_interpolation
+                .map_or(Ok(()), |interpolation| {
+                    interpolate_settings(&mut definition, interpolation)
+                })
+                .and_then(|_| PolicyOrPolicyGroup::from_definition(definition))
+                .map_err(|e| anyhow!(e.qualified(&format!("policies.{name}"))))?;
+            Ok((name, policy))
+        })
+        .collect()
 }
 
 /// Creates a `ClientTlsConfig` used by OTLP exporters based on the environment variables.
@@ -453,6 +931,8 @@ example:
     settings: {}
     allowedToMutate: true
     message: "my custom error message"
+    failurePolicy: Ignore
+    maxConcurrency: 4
     contextAwareResources:
         - apiVersion: v1
           kind: Namespace
@@ -469,13 +949,15 @@ group_policy:
         policy2:
             module: ghcr.io/kubewarden/policies/policy2:0.1.0
             settings: {}
+            policyMode: monitor
+            onTimeout: allow
 "#;
 
         let mut temp_file = NamedTempFile::new().unwrap();
         temp_file.write_all(policies_yaml.as_bytes()).unwrap();
         let file_path = temp_file.into_temp_path();
 
-        let policies = read_policies_file(file_path.as_ref()).unwrap();
+        let policies = read_policies_file(file_path.as_ref(), None).unwrap();
 
         let expected_policies = HashMap::from([
             (
@@ -497,6 +979,10 @@ group_policy:
                     ]),
                     message: Some("my custom error message".to_owned()),
                     timeout_eval_seconds: None,
+                    failure_policy: Some(FailurePolicy::Ignore),
+                    max_concurrency: NonZeroUsize::new(4),
+                    cache_ttl_seconds: None,
+                    match_conditions: None,
                 },
             ),
             (
@@ -504,6 +990,11 @@ group_policy:
                 PolicyOrPolicyGroup::PolicyGroup {
                     policy_mode: PolicyMode::Monitor,
                     expression: "true".to_owned(),
+                    expression_language: ExpressionLanguage::Rhai,
+                    members_evaluation: MembersEvaluation::Sequential,
+                    allowed_to_mutate: None,
+                    mutators: Vec::new(),
+                    trace: false,
                     message: "group policy message".to_owned(),
                     policies: HashMap::from([
                         (
@@ -513,6 +1004,8 @@ group_policy:
                                 settings: Some(PolicySettings::default()),
                                 context_aware_resources: BTreeSet::new(),
                                 timeout_eval_seconds: None,
+                                policy_mode: PolicyMode::Protect,
+                                on_timeout: OnTimeout::Error,
                             },
                         ),
                         (
@@ -522,9 +1015,14 @@ group_policy:
                                 settings: Some(PolicySettings::default()),
                                 context_aware_resources: BTreeSet::new(),
                                 timeout_eval_seconds: None,
+                                policy_mode: PolicyMode::Monitor,
+                                on_timeout: OnTimeout::Allow,
                             },
                         ),
                     ]),
+                    failure_policy: None,
+                    max_concurrency: None,
+                    match_conditions: None,
                 },
             ),
         ]);
@@ -581,6 +1079,163 @@ example:
         }
     }
 
+    #[rstest]
+    #[case::unknown_policy_mode(
+        r#"
+psp-caps:
+  module: ghcr.io/kubewarden/policies/psp-capabilities:v0.1.0
+  policyMode: protected
+"#,
+        "policies.psp-caps.policyMode: unknown variant `protected`"
+    )]
+    #[case::wrong_type(
+        r#"
+psp-caps:
+  module: ghcr.io/kubewarden/policies/psp-capabilities:v0.1.0
+  timeoutEvalSeconds: soon
+"#,
+        "policies.psp-caps.timeoutEvalSeconds: invalid type: string \"soon\""
+    )]
+    #[case::zero_max_concurrency(
+        r#"
+psp-caps:
+  module: ghcr.io/kubewarden/policies/psp-capabilities:v0.1.0
+  maxConcurrency: 0
+"#,
+        "policies.psp-caps.maxConcurrency: invalid value: integer `0`"
+    )]
+    #[case::group_without_expression(
+        r#"
+group:
+  message: "rejected"
+  policies: {}
+"#,
+        "policies.group: missing field `expression`"
+    )]
+    #[case::group_member_with_unknown_field(
+        r#"
+group:
+  expression: "member()"
+  message: "rejected"
+  policies:
+    member:
+      module: ghcr.io/kubewarden/policies/psp-capabilities:v0.1.0
+      allowedToMutate: true
+"#,
+        "policies.group.policies.member.allowedToMutate: unknown field `allowedToMutate`"
+    )]
+    #[case::not_a_mapping(
+        r#"
+psp-caps: ghcr.io/kubewarden/policies/psp-capabilities:v0.1.0
+"#,
+        "policies.psp-caps: expected a policy or a policy group definition"
+    )]
+    fn read_policies_file_reports_the_path_of_invalid_fields(
+        #[case] policies_yaml: &str,
+        #[case] expected_error: &str,
+    ) {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(policies_yaml.as_bytes()).unwrap();
+        let file_path = temp_file.into_temp_path();
+
+        let error = read_policies_file(file_path.as_ref(), None)
+            .unwrap_err()
+            .to_string();
+
+        assert!(
+            error.starts_with(expected_error),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test]
+    fn read_and_validate_policies_merges_files_and_directories() {
+        let policies_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            policies_dir.path().join("b.yml"),
+            "policy-b:\n  module: ghcr.io/kubewarden/tests/pod-privileged:v0.2.1\n",
+        )
+        .unwrap();
+        std::fs::write(
+            policies_dir.path().join("a.yaml"),
+            "policy-a:\n  module: ghcr.io/kubewarden/tests/pod-privileged:v0.2.1\n",
+        )
+        .unwrap();
+        std::fs::write(policies_dir.path().join("..data"), "not: yaml: at: all").unwrap();
+        std::fs::write(policies_dir.path().join("README.md"), "# policies").unwrap();
+
+        let mut policies_file = NamedTempFile::new().unwrap();
+        policies_file
+            .write_all(b"policy-c:\n  module: ghcr.io/kubewarden/tests/sleeping-policy:v0.1.0\n")
+            .unwrap();
+        let policies_file = policies_file.into_temp_path();
+
+        let policies = read_and_validate_policies(
+            &[
+                policies_dir.path().to_path_buf(),
+                policies_file.to_path_buf(),
+            ],
+            None,
+        )
+        .unwrap();
+
+        let mut names: Vec<&str> = policies.keys().map(|name| name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["policy-a", "policy-b", "policy-c"]);
+    }
+
+    #[test]
+    fn settings_are_not_interpolated_unless_enabled() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file
+            .write_all(
+                b"policy:\n  module: ghcr.io/kubewarden/tests/pod-privileged:v0.2.1\n  settings:\n    mirror: \"${env:HOME}\"\n",
+            )
+            .unwrap();
+        let file_path = temp_file.into_temp_path();
+
+        let policies = read_policies_file(file_path.as_ref(), None).unwrap();
+
+        let PolicyOrPolicyGroupSettings::Policy(settings) = policies["policy"].settings().unwrap()
+        else {
+            panic!("expected the settings of a policy");
+        };
+        assert_eq!(settings.0.get("mirror"), Some(&json!("${env:HOME}")));
+    }
+
+    #[test]
+    fn read_and_validate_policies_rejects_duplicated_policies() {
+        let policies_dir = tempfile::tempdir().unwrap();
+        let policy_yaml = "policy:\n  module: ghcr.io/kubewarden/tests/pod-privileged:v0.2.1\n";
+        std::fs::write(policies_dir.path().join("a.yml"), policy_yaml).unwrap();
+        std::fs::write(policies_dir.path().join("b.yml"), policy_yaml).unwrap();
+
+        let error = read_and_validate_policies(&[policies_dir.path().to_path_buf()], None)
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("policy 'policy' is defined both inside of"));
+        assert!(error.contains("a.yml"));
+        assert!(error.contains("b.yml"));
+    }
+
+    #[test]
+    fn policies_json_schema_describes_policies_and_groups() {
+        let schema = serde_json::to_value(policies_json_schema()).unwrap();
+
+        assert_eq!(
+            schema["additionalProperties"]["$ref"],
+            "#/$defs/PolicyOrPolicyGroup"
+        );
+        let any_of = &schema["$defs"]["PolicyOrPolicyGroup"]["anyOf"];
+        assert_eq!(any_of.as_array().unwrap().len(), 2);
+        assert_eq!(any_of[0]["required"], json!(["module"]));
+        assert_eq!(
+            any_of[1]["properties"]["policyMode"]["enum"],
+            json!(["monitor", "protect"])
+        );
+    }
+
     #[test]
     fn boolean_flags() {
         let policies_yaml = r#"
@@ -618,6 +1273,35 @@ example:
         }
     }
 
+    #[test]
+    fn subcommands_inherit_server_flags() {
+        let policies_yaml = r#"
+---
+example:
+  module: file:///tmp/namespace-validate-policy.wasm
+  settings: {}
+"#;
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(policies_yaml.as_bytes()).unwrap();
+        let file_path = temp_file.into_temp_path();
+        let policies_flag = format!("--policies={}", file_path.to_str().unwrap());
+
+        let matches = cli::build_cli()
+            .try_get_matches_from([
+                "policy-server",
+                "test",
+                &policies_flag,
+                "--policy-timeout=5",
+                "--request=pod.json",
+            ])
+            .unwrap();
+        let sub_matches = matches.subcommand_matches("test").unwrap();
+        let config = Config::from_args(sub_matches).unwrap();
+
+        assert!(config.policies.contains_key("example"));
+        assert_eq!(config.policy_evaluation_limit_seconds, Some(5));
+    }
+
     #[rstest]
     #[case::all_good(
         r#"
@@ -663,6 +1347,59 @@ group_policy:
     policy2:
       module: file:///tmp/namespace-validate-policy.wasm
       settings: {}
+"#,
+        false
+    )]
+    #[case::mutating_policy_group(
+        r#"
+---
+group_policy:
+  expression: "policy2()"
+  message: "group policy message"
+  allowedToMutate: true
+  mutators: [policy1]
+  policies:
+    policy1:
+      module: file:///tmp/namespace-validate-policy.wasm
+      settings: {}
+    policy2:
+      module: file:///tmp/namespace-validate-policy.wasm
+      settings: {}
+"#,
+        true
+    )]
+    #[case::policy_group_with_unknown_mutator(
+        r#"
+---
+group_policy:
+  expression: "policy2()"
+  message: "group policy message"
+  allowedToMutate: true
+  mutators: [policy3]
+  policies:
+    policy1:
+      module: file:///tmp/namespace-validate-policy.wasm
+      settings: {}
+    policy2:
+      module: file:///tmp/namespace-validate-policy.wasm
+      settings: {}
+"#,
+        false
+    )]
+    #[case::policy_group_with_mutators_not_allowed_to_mutate(
+        r#"
+---
+group_policy:
+  expression: "policy2()"
+  message: "group policy message"
+  mutators: [policy1]
+  policies:
+    policy1:
+      module: file:///tmp/namespace-validate-policy.wasm
+      settings: {}
+    policy2:
+      module: file:///tmp/namespace-validate-policy.wasm
+      settings: {}
 "#,
         false
     )]
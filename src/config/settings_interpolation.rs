@@ -0,0 +1,300 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use super::PolicyDefinitionError;
+
+/// The references that can be resolved inside of the settings of the policies.
+///
+/// Nothing is resolved when the interpolation is not enabled: the settings are given
+/// to the policies as they are written.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SettingsInterpolation {
+    /// The directories holding the files that can be referenced
+    pub allowed_dirs: Vec<PathBuf>,
+    /// The prefixes of the names of the environment variables that can be referenced
+    pub allowed_env_prefixes: Vec<String>,
+}
+
+impl SettingsInterpolation {
+    /// Resolves a reference, the environment variables are read with `lookup_env`
+    fn resolve(
+        &self,
+        reference: &str,
+        lookup_env: impl Fn(&str) -> Result<String, env::VarError>,
+    ) -> Result<String, String> {
+        match reference.split_once(':') {
+            Some(("env", name)) => {
+                if !self
+                    .allowed_env_prefixes
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix.as_str()))
+                {
+                    return Err(format!(
+                        "cannot resolve '${{{reference}}}': environment variable {name} does not start with any of the allowed prefixes"
+                    ));
+                }
+                lookup_env(name).map_err(|e| {
+                    format!("cannot resolve '${{{reference}}}': environment variable {name}: {e}")
+                })
+            }
+            Some(("file", path)) => {
+                let canonical_path = fs::canonicalize(path).map_err(|e| {
+                    format!("cannot resolve '${{{reference}}}': cannot read {path}: {e}")
+                })?;
+                if !self.is_allowed_file(&canonical_path) {
+                    return Err(format!(
+                        "cannot resolve '${{{reference}}}': {path} is not inside of the allowed directories"
+                    ));
+                }
+                fs::read_to_string(&canonical_path).map_err(|e| {
+                    format!("cannot resolve '${{{reference}}}': cannot read {path}: {e}")
+                })
+            }
+            _ => Err(format!(
+                "cannot resolve '${{{reference}}}': unknown reference, expected '${{env:NAME}}' or '${{file:PATH}}'"
+            )),
+        }
+    }
+
+    /// The symlinks and the `..` components are resolved before the check, they cannot
+    /// be used to reach a file outside of the allowed directories
+    fn is_allowed_file(&self, canonical_path: &Path) -> bool {
+        self.allowed_dirs
+            .iter()
+            .filter_map(|dir| fs::canonicalize(dir).ok())
+            .any(|dir| canonical_path.starts_with(dir))
+    }
+}
+
+/// Resolves the references found inside of the `settings` of a policy definition, and
+/// inside of the `settings` of the members of a policy group definition.
+///
+/// The string values of the settings can reference:
+/// - an environment variable: `${env:REGISTRY_MIRROR}`
+/// - the contents of a file: `${file:/var/run/secrets/pubkey.pem}`
+///
+/// A literal `${` is written as `$${`.
+pub(super) fn interpolate_settings(
+    definition: &mut serde_yaml::Value,
+    interpolation: &SettingsInterpolation,
+) -> Result<(), PolicyDefinitionError> {
+    let resolve = |reference: &str| interpolation.resolve(reference, |name| env::var(name));
+
+    let Some(definition) = definition.as_mapping_mut() else {
+        return Ok(());
+    };
+
+    if let Some(settings) = definition.get_mut("settings") {
+        interpolate_value(settings, "settings", &resolve)?;
+    }
+
+    if let Some(members) = definition
+        .get_mut("policies")
+        .and_then(|members| members.as_mapping_mut())
+    {
+        for (member_name, member) in members.iter_mut() {
+            let Some(settings) = member.get_mut("settings") else {
+                continue;
+            };
+            let member_name = member_name.as_str().unwrap_or_default();
+            interpolate_value(
+                settings,
+                &format!("policies.{member_name}.settings"),
+                &resolve,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn interpolate_value(
+    value: &mut serde_yaml::Value,
+    path: &str,
+    resolve: &dyn Fn(&str) -> Result<String, String>,
+) -> Result<(), PolicyDefinitionError> {
+    match value {
+        serde_yaml::Value::String(string) => {
+            *string =
+                interpolate_string(string, resolve).map_err(|message| PolicyDefinitionError {
+                    path: path.to_owned(),
+                    message,
+                })?;
+        }
+        serde_yaml::Value::Sequence(sequence) => {
+            for (index, item) in sequence.iter_mut().enumerate() {
+                interpolate_value(item, &format!("{path}[{index}]"), resolve)?;
+            }
+        }
+        serde_yaml::Value::Mapping(mapping) => {
+            for (key, item) in mapping.iter_mut() {
+                let key = key.as_str().unwrap_or_default();
+                interpolate_value(item, &format!("{path}.{key}"), resolve)?;
+            }
+        }
+        serde_yaml::Value::Tagged(tagged) => interpolate_value(&mut tagged.value, path, resolve)?,
+        serde_yaml::Value::Null | serde_yaml::Value::Bool(_) | serde_yaml::Value::Number(_) => {}
+    }
+
+    Ok(())
+}
+
+/// Replaces all the references found inside of `input` with the values returned by `resolve`
+fn interpolate_string(
+    input: &str,
+    resolve: &dyn Fn(&str) -> Result<String, String>,
+) -> Result<String, String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(escaped) = rest.strip_prefix("$${") {
+            output.push_str("${");
+            rest = escaped;
+        } else if let Some(reference) = rest.strip_prefix("${") {
+            let end = reference
+                .find('}')
+                .ok_or_else(|| format!("unterminated reference in '{input}'"))?;
+            output.push_str(&resolve(&reference[..end])?);
+            rest = &reference[end + 1..];
+        } else {
+            output.push('$');
+            rest = &rest[1..];
+        }
+    }
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use tempfile::NamedTempFile;
+
+    fn lookup_env(name: &str) -> Result<String, env::VarError> {
+        match name {
+            "POLICY_SERVER_MIRROR" => Ok("mirror.example.com".to_owned()),
+            "SECRET_TOKEN" => Ok("secret".to_owned()),
+            _ => Err(env::VarError::NotPresent),
+        }
+    }
+
+    fn interpolation(allowed_dirs: Vec<PathBuf>) -> SettingsInterpolation {
+        SettingsInterpolation {
+            allowed_dirs,
+            allowed_env_prefixes: vec!["POLICY_SERVER_".to_owned()],
+        }
+    }
+
+    #[rstest]
+    #[case::no_references("registry.example.com", "registry.example.com")]
+    #[case::dollar_sign("costs $5", "costs $5")]
+    #[case::escaped("$${env:HOME}", "${env:HOME}")]
+    #[case::env(
+        "${env:POLICY_SERVER_MIRROR}/kubewarden",
+        "mirror.example.com/kubewarden"
+    )]
+    fn interpolate_string_resolves_references(#[case] input: &str, #[case] expected: &str) {
+        let interpolation = interpolation(Vec::new());
+        let resolve = |reference: &str| interpolation.resolve(reference, lookup_env);
+
+        assert_eq!(interpolate_string(input, &resolve).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::unset_env("${env:POLICY_SERVER_UNSET}")]
+    #[case::env_not_allowed("${env:SECRET_TOKEN}")]
+    #[case::missing_file("${file:/does/not/exist.pem}")]
+    #[case::file_not_allowed("${file:/etc/hosts}")]
+    #[case::unknown_provider("${vault:secret}")]
+    fn interpolate_string_reports_unresolved_references(#[case] input: &str) {
+        let interpolation = interpolation(vec![env::temp_dir()]);
+        let resolve = |reference: &str| interpolation.resolve(reference, lookup_env);
+
+        let error = interpolate_string(input, &resolve).unwrap_err();
+
+        assert!(error.contains(input), "unexpected error: {error}");
+    }
+
+    #[test]
+    fn files_outside_of_the_allowed_directories_cannot_be_reached() {
+        let allowed_dir = tempfile::tempdir().unwrap();
+        let secret_file = NamedTempFile::new().unwrap();
+        let reference = format!(
+            "file:{}/../{}",
+            allowed_dir.path().display(),
+            secret_file.path().file_name().unwrap().to_str().unwrap()
+        );
+
+        let error = interpolation(vec![allowed_dir.path().to_path_buf()])
+            .resolve(&reference, lookup_env)
+            .unwrap_err();
+
+        assert!(
+            error.contains("is not inside of the allowed directories"),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test]
+    fn interpolate_settings_reads_files_inside_of_groups() {
+        let pubkey_dir = tempfile::tempdir().unwrap();
+        let pubkey_path = pubkey_dir.path().join("pubkey.pem");
+        fs::write(&pubkey_path, "PUBLIC KEY").unwrap();
+
+        let mut definition: serde_yaml::Value = serde_yaml::from_str(&format!(
+            r#"
+expression: "signed()"
+message: "not signed"
+policies:
+  signed:
+    module: ghcr.io/kubewarden/policies/verify-image-signatures:v0.3.0
+    settings:
+      keys:
+        - "${{file:{}}}"
+"#,
+            pubkey_path.display()
+        ))
+        .unwrap();
+
+        interpolate_settings(
+            &mut definition,
+            &interpolation(vec![pubkey_dir.path().to_path_buf()]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            definition["policies"]["signed"]["settings"]["keys"][0],
+            serde_yaml::Value::from("PUBLIC KEY")
+        );
+    }
+
+    #[test]
+    fn interpolate_settings_reports_the_path_of_unresolved_references() {
+        let mut definition: serde_yaml::Value = serde_yaml::from_str(
+            r#"
+module: ghcr.io/kubewarden/policies/allowed-registries:v0.1.0
+settings:
+  registries:
+    - "${env:POLICY_SERVER_INTERPOLATION_UNSET}"
+"#,
+        )
+        .unwrap();
+
+        let error = interpolate_settings(&mut definition, &interpolation(Vec::new())).unwrap_err();
+
+        assert_eq!(error.path, "settings.registries[0]");
+        assert!(
+            error
+                .message
+                .contains("${env:POLICY_SERVER_INTERPOLATION_UNSET}")
+        );
+    }
+}
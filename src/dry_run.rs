@@ -0,0 +1,164 @@
+pub mod test_suite;
+
+use std::{collections::BTreeMap, fmt, path::Path, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use policy_evaluator::policy_evaluator::ValidateRequest;
+use tokio::{sync::oneshot, task};
+
+use crate::{
+    PolicyEvaluationComponents,
+    api::{
+        admission_review::AdmissionReviewRequest,
+        batch_review::{BatchReviewResponse, BatchReviewResult},
+        handlers::handle_evaluation_error,
+        service::{RequestOrigin, evaluate},
+    },
+    config::Config,
+    evaluation::EvaluationEnvironment,
+    start_policy_timeout_protection,
+};
+
+/// Evaluate the AdmissionReview stored inside of `request_file` against the policies
+/// defined by the configuration, without starting the server.
+///
+/// The request is evaluated against the given policies, or against all of them when
+/// `policy_ids` is not provided. The policies that cannot be loaded are reported as
+/// evaluation errors, instead of aborting the whole run.
+pub async fn evaluate_request_file(
+    mut config: Config,
+    request_file: &Path,
+    policy_ids: Option<Vec<String>>,
+) -> Result<BatchReviewResponse> {
+    let validate_request = read_request_file(request_file)?;
+
+    config.continue_on_errors = true;
+    let offline_environment = OfflineEvaluationEnvironment::new(config).await?;
+
+    let evaluation_environment = offline_environment.evaluation_environment.clone();
+    let policy_ids = policy_ids.unwrap_or_else(|| evaluation_environment.policy_ids());
+    let results = task::spawn_blocking(move || {
+        policy_ids
+            .into_iter()
+            .map(|policy_id| {
+                let result = evaluate(
+                    evaluation_environment.clone(),
+                    &policy_id,
+                    &validate_request,
+                    RequestOrigin::Validate,
+                    false,
+                );
+                // The errors are reported like the `/validate_batch` endpoint does, the
+                // details are not redacted: the output is meant for the user running the command
+                BatchReviewResult::new(
+                    policy_id,
+                    result.map_err(|error| handle_evaluation_error(error, false).1),
+                )
+            })
+            .collect()
+    })
+    .await?;
+
+    offline_environment.shutdown().await;
+
+    Ok(BatchReviewResponse { results })
+}
+
+/// The outcome of the validation of the policies defined by the configuration
+#[derive(Debug)]
+pub struct ConfigCheckReport {
+    /// The error of each policy, indexed by policy ID. Policy group members are included.
+    pub policies: BTreeMap<String, Option<String>>,
+}
+
+impl ConfigCheckReport {
+    pub fn passed(&self) -> bool {
+        self.policies.values().all(Option::is_none)
+    }
+}
+
+impl fmt::Display for ConfigCheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (policy_id, error) in &self.policies {
+            match error {
+                None => writeln!(f, "ok     {policy_id}")?,
+                Some(error) => writeln!(f, "error  {policy_id}: {error}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Load all the policies defined by the configuration and validate their settings,
+/// including the expressions of the policy groups, without starting the server.
+pub async fn check_config(mut config: Config) -> Result<ConfigCheckReport> {
+    // Keep going on errors, all the broken policies must be reported
+    config.continue_on_errors = true;
+    let offline_environment = OfflineEvaluationEnvironment::new(config).await?;
+
+    let evaluation_environment = offline_environment.evaluation_environment.clone();
+    let policies =
+        task::spawn_blocking(move || evaluation_environment.validate_all_settings()).await?;
+
+    offline_environment.shutdown().await;
+
+    Ok(ConfigCheckReport { policies })
+}
+
+/// Read the AdmissionReview stored inside of the given JSON file
+pub(crate) fn read_request_file(request_file: &Path) -> Result<ValidateRequest> {
+    let admission_review: AdmissionReviewRequest = serde_json::from_str(
+        &std::fs::read_to_string(request_file)
+            .map_err(|e| anyhow!("cannot read request file {request_file:?}: {e}"))?,
+    )
+    .map_err(|e| anyhow!("cannot parse request file {request_file:?}: {e}"))?;
+
+    Ok(ValidateRequest::AdmissionRequest(Box::new(
+        admission_review.request,
+    )))
+}
+
+/// An `EvaluationEnvironment` built outside of the server, together with the
+/// `CallbackHandler` task used by the policies
+pub(crate) struct OfflineEvaluationEnvironment {
+    pub(crate) evaluation_environment: Arc<EvaluationEnvironment>,
+    callback_handler: task::JoinHandle<()>,
+    callback_handler_shutdown_channel_tx: oneshot::Sender<()>,
+}
+
+impl OfflineEvaluationEnvironment {
+    /// Download, precompile and load the policies defined by the configuration.
+    ///
+    /// The offline runs must work without a Kubernetes cluster, like inside of a CI pipeline:
+    /// a failure to connect to the cluster is ignored, and the Namespaces matching the label
+    /// selector of the configuration are not looked up.
+    pub(crate) async fn new(mut config: Config) -> Result<Self> {
+        config.ignore_kubernetes_connection_failure = true;
+        config.always_accept_admission_reviews_on_namespace_selector = None;
+
+        let PolicyEvaluationComponents {
+            mut callback_handler,
+            callback_handler_shutdown_channel_tx,
+            engine,
+            mut policies_loader,
+        } = PolicyEvaluationComponents::new(&config).await?;
+
+        let callback_handler = tokio::spawn(async move { callback_handler.loop_eval().await });
+        start_policy_timeout_protection(&engine, config.policy_evaluation_limit_seconds);
+
+        let evaluation_environment = policies_loader.load(&config.policies).await?;
+
+        Ok(Self {
+            evaluation_environment: Arc::new(evaluation_environment),
+            callback_handler,
+            callback_handler_shutdown_channel_tx,
+        })
+    }
+
+    /// Stop the `CallbackHandler` task
+    pub(crate) async fn shutdown(self) {
+        // The task could have already stopped, there's nothing to do in that case
+        let _ = self.callback_handler_shutdown_channel_tx.send(());
+        let _ = self.callback_handler.await;
+    }
+}
@@ -0,0 +1,385 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use policy_evaluator::admission_response::AdmissionResponse;
+use serde::Deserialize;
+use tokio::task;
+
+use crate::{
+    api::service::{RequestOrigin, evaluate},
+    config::Config,
+    dry_run::{OfflineEvaluationEnvironment, read_request_file},
+};
+
+/// A suite of test cases, each one evaluating a request against a policy
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct TestSuite {
+    pub tests: Vec<TestCase>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct TestCase {
+    pub name: String,
+    /// The ID of the policy to evaluate
+    pub policy: String,
+    /// JSON file holding the AdmissionReview to evaluate. Relative paths are
+    /// resolved against the directory containing the test suite.
+    pub request: PathBuf,
+    pub expect: Expectation,
+}
+
+/// The expected outcome of a test case. `allowed` is required and always checked, the
+/// other fields are checked only when they are set.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct Expectation {
+    pub allowed: bool,
+    pub message: Option<String>,
+    pub code: Option<u16>,
+    /// The JSONPatch produced by the policy
+    pub patch: Option<serde_json::Value>,
+}
+
+/// The outcome of a test case
+#[derive(Debug)]
+pub struct TestCaseResult {
+    pub name: String,
+    pub policy: String,
+    /// The reasons why the test case failed, empty when it passed
+    pub failures: Vec<String>,
+    pub duration: Duration,
+}
+
+impl TestCaseResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// The outcome of a test suite
+#[derive(Debug)]
+pub struct TestSuiteReport {
+    pub name: String,
+    pub results: Vec<TestCaseResult>,
+}
+
+impl TestSuiteReport {
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.passed())
+    }
+
+    fn failures(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|result| !result.passed())
+            .count()
+    }
+
+    /// Render the report using the Test Anything Protocol
+    pub fn to_tap(&self) -> String {
+        let mut output = format!("TAP version 13\n1..{}\n", self.results.len());
+        for (index, result) in self.results.iter().enumerate() {
+            let status = if result.passed() { "ok" } else { "not ok" };
+            output.push_str(&format!(
+                "{status} {} - {}\n",
+                index + 1,
+                result.name.replace('#', "\\#")
+            ));
+            for failure in &result.failures {
+                for line in failure.lines() {
+                    output.push_str(&format!("# {line}\n"));
+                }
+            }
+        }
+        output
+    }
+
+    /// Render the report using the JUnit XML format
+    pub fn to_junit(&self) -> String {
+        let total_time: f64 = self
+            .results
+            .iter()
+            .map(|result| result.duration.as_secs_f64())
+            .sum();
+
+        let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        output.push_str(&format!(
+            "<testsuites tests=\"{}\" failures=\"{}\" time=\"{total_time:.3}\">\n",
+            self.results.len(),
+            self.failures(),
+        ));
+        output.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{total_time:.3}\">\n",
+            xml_escape(&self.name),
+            self.results.len(),
+            self.failures(),
+        ));
+        for result in &self.results {
+            let testcase = format!(
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                xml_escape(&result.name),
+                xml_escape(&result.policy),
+                result.duration.as_secs_f64(),
+            );
+            if result.passed() {
+                output.push_str(&format!("{testcase}/>\n"));
+                continue;
+            }
+            output.push_str(&format!("{testcase}>\n"));
+            output.push_str(&format!(
+                "      <failure message=\"{}\">{}</failure>\n",
+                xml_escape(&result.failures[0]),
+                xml_escape(&result.failures.join("\n")),
+            ));
+            output.push_str("    </testcase>\n");
+        }
+        output.push_str("  </testsuite>\n</testsuites>\n");
+        output
+    }
+}
+
+/// Run all the test cases defined inside of the `suite_file`, using the policies
+/// defined by the configuration
+pub async fn run_test_suite(mut config: Config, suite_file: &Path) -> Result<TestSuiteReport> {
+    let test_suite: TestSuite = serde_yaml::from_str(
+        &fs::read_to_string(suite_file)
+            .map_err(|e| anyhow!("cannot read test suite {suite_file:?}: {e}"))?,
+    )
+    .map_err(|e| anyhow!("cannot parse test suite {suite_file:?}: {e}"))?;
+    let base_dir = suite_file
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    // The policies that cannot be loaded make their test cases fail
+    config.continue_on_errors = true;
+    let offline_environment = OfflineEvaluationEnvironment::new(config).await?;
+
+    let evaluation_environment = offline_environment.evaluation_environment.clone();
+    let results = task::spawn_blocking(move || {
+        test_suite
+            .tests
+            .into_iter()
+            .map(|test_case| {
+                let start = Instant::now();
+                let failures = match read_request_file(&base_dir.join(&test_case.request)) {
+                    Ok(validate_request) => match evaluate(
+                        evaluation_environment.clone(),
+                        &test_case.policy,
+                        &validate_request,
+                        RequestOrigin::Validate,
+                        false,
+                    ) {
+                        Ok(response) => check_expectation(&test_case.expect, &response),
+                        Err(e) => vec![format!("evaluation failed: {e}")],
+                    },
+                    Err(e) => vec![e.to_string()],
+                };
+
+                TestCaseResult {
+                    name: test_case.name,
+                    policy: test_case.policy,
+                    failures,
+                    duration: start.elapsed(),
+                }
+            })
+            .collect()
+    })
+    .await?;
+
+    offline_environment.shutdown().await;
+
+    Ok(TestSuiteReport {
+        name: suite_file.display().to_string(),
+        results,
+    })
+}
+
+/// Compare the response against the expectation, returns the differences found
+fn check_expectation(expect: &Expectation, response: &AdmissionResponse) -> Vec<String> {
+    let mut failures = Vec::new();
+
+    if response.allowed != expect.allowed {
+        failures.push(format!(
+            "expected allowed to be {}, got {}",
+            expect.allowed, response.allowed
+        ));
+    }
+
+    let status = response.status.as_ref();
+    if let Some(expected_message) = &expect.message {
+        let message = status.and_then(|status| status.message.as_ref());
+        if message != Some(expected_message) {
+            failures.push(format!(
+                "expected message {expected_message:?}, got {message:?}"
+            ));
+        }
+    }
+    if let Some(expected_code) = expect.code {
+        let code = status.and_then(|status| status.code);
+        if code != Some(expected_code) {
+            failures.push(format!("expected code {expected_code}, got {code:?}"));
+        }
+    }
+
+    if let Some(expected_patch) = &expect.patch {
+        match decode_patch(response.patch.as_deref()) {
+            Ok(Some(patch)) if &patch == expected_patch => {}
+            Ok(Some(patch)) => {
+                failures.push(format!("expected patch {expected_patch}, got {patch}"))
+            }
+            Ok(None) => failures.push(format!("expected patch {expected_patch}, got none")),
+            Err(e) => failures.push(e.to_string()),
+        }
+    }
+
+    failures
+}
+
+/// The patch of an `AdmissionResponse` is a base64 encoded JSONPatch
+fn decode_patch(patch: Option<&str>) -> Result<Option<serde_json::Value>> {
+    patch
+        .map(|patch| {
+            let patch = STANDARD
+                .decode(patch)
+                .map_err(|e| anyhow!("cannot decode patch: {e}"))?;
+            serde_json::from_slice(&patch).map_err(|e| anyhow!("cannot parse patch: {e}"))
+        })
+        .transpose()
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use policy_evaluator::admission_response::AdmissionResponseStatus;
+    use rstest::*;
+    use serde_json::json;
+
+    fn rejected_response() -> AdmissionResponse {
+        AdmissionResponse {
+            uid: "uid".to_owned(),
+            allowed: false,
+            status: Some(AdmissionResponseStatus {
+                message: Some("privileged containers are not allowed".to_owned()),
+                code: Some(400),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[case::matching(
+        Expectation {
+            allowed: false,
+            message: Some("privileged containers are not allowed".to_owned()),
+            code: Some(400),
+            patch: None,
+        },
+        0
+    )]
+    #[case::only_allowed_is_checked(Expectation::default(), 0)]
+    #[case::everything_differs(
+        Expectation {
+            allowed: true,
+            message: Some("something else".to_owned()),
+            code: Some(500),
+            patch: Some(json!([])),
+        },
+        4
+    )]
+    fn check_expectation_against_response(
+        #[case] expect: Expectation,
+        #[case] expected_failures: usize,
+    ) {
+        let failures = check_expectation(&expect, &rejected_response());
+
+        assert_eq!(failures.len(), expected_failures, "{failures:?}");
+    }
+
+    #[test]
+    fn check_expected_patch() {
+        let patch = json!([{"op": "add", "path": "/metadata/labels/foo", "value": "bar"}]);
+        let response = AdmissionResponse {
+            uid: "uid".to_owned(),
+            allowed: true,
+            patch: Some(STANDARD.encode(patch.to_string())),
+            ..Default::default()
+        };
+        let mut expect = Expectation {
+            allowed: true,
+            patch: Some(patch),
+            ..Default::default()
+        };
+
+        assert!(check_expectation(&expect, &response).is_empty());
+
+        expect.patch = Some(json!([]));
+        assert_eq!(check_expectation(&expect, &response).len(), 1);
+    }
+
+    fn report() -> TestSuiteReport {
+        TestSuiteReport {
+            name: "suite.yml".to_owned(),
+            results: vec![
+                TestCaseResult {
+                    name: "privileged pod is rejected".to_owned(),
+                    policy: "pod-privileged".to_owned(),
+                    failures: vec![],
+                    duration: Duration::from_millis(10),
+                },
+                TestCaseResult {
+                    name: "pod <unprivileged> is accepted".to_owned(),
+                    policy: "pod-privileged".to_owned(),
+                    failures: vec!["expected allowed to be true, got false".to_owned()],
+                    duration: Duration::from_millis(20),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn tap_report() {
+        assert_eq!(
+            report().to_tap(),
+            "TAP version 13\n\
+             1..2\n\
+             ok 1 - privileged pod is rejected\n\
+             not ok 2 - pod <unprivileged> is accepted\n\
+             # expected allowed to be true, got false\n"
+        );
+    }
+
+    #[test]
+    fn junit_report() {
+        let junit = report().to_junit();
+
+        assert!(
+            junit.contains(r#"<testsuite name="suite.yml" tests="2" failures="1" time="0.030">"#)
+        );
+        assert!(junit.contains(
+            r#"<testcase name="privileged pod is rejected" classname="pod-privileged" time="0.010"/>"#
+        ));
+        assert!(junit.contains(r#"<testcase name="pod &lt;unprivileged&gt; is accepted""#));
+        assert!(junit.contains(
+            r#"<failure message="expected allowed to be true, got false">expected allowed to be true, got false</failure>"#
+        ));
+    }
+}
@@ -1,9 +1,16 @@
+pub(crate) mod always_accepted_namespaces;
+pub(crate) mod evaluation_cache;
 mod evaluation_environment;
+pub(crate) mod match_conditions;
 mod policy_evaluation_settings;
+mod policy_group_evaluator;
+pub(crate) mod policy_info;
+pub(crate) mod policy_timeout;
 pub(crate) mod precompiled_policy;
+pub(crate) mod validation_response;
 
 // This is required to mock the `EvaluationEnvironment` inside of our tests
 #[mockall_double::double]
 pub(crate) use evaluation_environment::EvaluationEnvironment;
 
-pub(crate) use evaluation_environment::EvaluationEnvironmentBuilder;
+pub(crate) use evaluation_environment::{EvaluationEnvironmentBuilder, PolicyEvaluatorPreKey};
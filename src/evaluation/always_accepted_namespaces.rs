@@ -0,0 +1,160 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{Result, anyhow};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use k8s_openapi::api::core::v1::Namespace;
+use policy_evaluator::kube::{self, Api, api::ListParams};
+use tokio::time;
+use tracing::{debug, warn};
+
+/// How often the Namespaces matching the label selector are listed again
+const NAMESPACE_SELECTOR_REFRESH_INTERVAL: time::Duration = time::Duration::from_secs(30);
+
+/// The Namespaces where Policy Server doesn't operate. All the requests involving these
+/// Namespaces are going to be accepted. This is usually done to prevent user policies from
+/// messing with the components of the Kubewarden stack, or with the ones of the cluster.
+///
+/// The Namespaces are defined by their names, by glob patterns (like `kube-*`) and by a
+/// label selector. The Namespaces matching the label selector are resolved by a background
+/// task, the instances obtained by cloning share the same list of resolved Namespaces.
+#[derive(Clone)]
+pub(crate) struct AlwaysAcceptedNamespaces {
+    /// The names and the glob patterns provided by the user
+    patterns: GlobSet,
+    /// The Namespaces matching the label selector provided by the user
+    selected: Arc<RwLock<HashSet<String>>>,
+}
+
+impl Default for AlwaysAcceptedNamespaces {
+    fn default() -> Self {
+        Self {
+            patterns: GlobSet::empty(),
+            selected: Arc::new(RwLock::new(HashSet::new())),
+        }
+    }
+}
+
+impl AlwaysAcceptedNamespaces {
+    /// Create a new instance matching the given Namespace names and glob patterns
+    pub(crate) fn new(patterns: &[String]) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(
+                Glob::new(pattern)
+                    .map_err(|e| anyhow!("invalid namespace pattern '{pattern}': {e}"))?,
+            );
+        }
+
+        Ok(Self {
+            patterns: builder
+                .build()
+                .map_err(|e| anyhow!("invalid namespace patterns: {e}"))?,
+            ..Default::default()
+        })
+    }
+
+    /// Returns `true` when all the requests made inside of the given Namespace must be accepted
+    pub(crate) fn contains(&self, namespace: &str) -> bool {
+        self.patterns.is_match(namespace)
+            || self
+                .selected
+                .read()
+                .expect("cannot acquire read lock on the selected namespaces")
+                .contains(namespace)
+    }
+
+    /// Replace the Namespaces matching the label selector
+    fn set_selected(&self, namespaces: HashSet<String>) {
+        *self
+            .selected
+            .write()
+            .expect("cannot acquire write lock on the selected namespaces") = namespaces;
+    }
+
+    /// Resolve the Namespaces matching the given label selector, then keep them in sync
+    /// by listing them again periodically.
+    ///
+    /// The first listing is done right away, an error is returned when it fails. The
+    /// failures of the following ones are just logged, the last known Namespaces are kept.
+    pub(crate) async fn watch_label_selector(
+        &self,
+        client: kube::Client,
+        label_selector: String,
+    ) -> Result<()> {
+        let api: Api<Namespace> = Api::all(client);
+        let namespaces = list_namespaces(&api, &label_selector).await.map_err(|e| {
+            anyhow!("cannot list the namespaces matching the selector '{label_selector}': {e}")
+        })?;
+        self.set_selected(namespaces);
+
+        let always_accepted_namespaces = self.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(NAMESPACE_SELECTOR_REFRESH_INTERVAL);
+            // The first tick completes immediately, the namespaces have just been listed
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                match list_namespaces(&api, &label_selector).await {
+                    Ok(namespaces) => {
+                        debug!(?namespaces, "always accepted namespaces refreshed");
+                        always_accepted_namespaces.set_selected(namespaces);
+                    }
+                    Err(e) => {
+                        warn!(error = %e, %label_selector, "cannot refresh the always accepted namespaces");
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+async fn list_namespaces(api: &Api<Namespace>, label_selector: &str) -> Result<HashSet<String>> {
+    let namespaces = api
+        .list_metadata(&ListParams::default().labels(label_selector))
+        .await?;
+
+    Ok(namespaces
+        .items
+        .into_iter()
+        .filter_map(|namespace| namespace.metadata.name)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case::name("kubewarden", true)]
+    #[case::glob("kube-system", true)]
+    #[case::selected("monitoring", true)]
+    #[case::not_matching("default", false)]
+    #[case::partial_name("kubewarden-tests", false)]
+    fn contains_names_patterns_and_selected_namespaces(
+        #[case] namespace: &str,
+        #[case] expected: bool,
+    ) {
+        let always_accepted_namespaces =
+            AlwaysAcceptedNamespaces::new(&["kubewarden".to_owned(), "kube-*".to_owned()]).unwrap();
+        always_accepted_namespaces
+            .clone()
+            .set_selected(HashSet::from(["monitoring".to_owned()]));
+
+        assert_eq!(always_accepted_namespaces.contains(namespace), expected);
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        let error = AlwaysAcceptedNamespaces::new(&["kube-[".to_owned()])
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("kube-["), "unexpected error: {error}");
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    num::NonZeroUsize,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+use policy_evaluator::{
+    admission_request::AdmissionRequest, admission_response::AdmissionResponse,
+    admission_response_handler::policy_id::PolicyID, policy_evaluator::PolicySettings,
+};
+use sha2::{Digest, Sha256};
+
+/// The fields of the admission request that do not influence the outcome of the evaluation.
+/// They are ignored when computing the key of the request, this allows a dry-run request and
+/// the real one to share the same response.
+const IGNORED_REQUEST_FIELDS: [&str; 2] = ["uid", "dryRun"];
+
+/// A bounded cache of the responses given by the policies, the least recently used entries
+/// are evicted first. Each response expires after the time to live of its policy.
+///
+/// The cache is shared by all the `EvaluationEnvironment` instances built by the server. The
+/// keys include the digests of the Wasm module and of the settings of the policy, hence the
+/// policies that changed after a reload do not reuse the responses of their previous version.
+pub(crate) struct EvaluationCache {
+    responses: Mutex<LruCache<String, CachedResponse>>,
+}
+
+struct CachedResponse {
+    response: AdmissionResponse,
+    expires_at: Instant,
+}
+
+impl EvaluationCache {
+    pub(crate) fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            responses: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Returns the response cached for the given key, answering to the request with the
+    /// given UID. The expired response is removed.
+    pub(crate) fn get(&self, key: &str, uid: &str) -> Option<AdmissionResponse> {
+        let mut responses = self
+            .responses
+            .lock()
+            .expect("cannot acquire lock on the evaluation cache");
+        if responses
+            .peek(key)
+            .is_some_and(|cached| cached.expires_at <= Instant::now())
+        {
+            responses.pop(key);
+            return None;
+        }
+
+        responses.get(key).map(|cached| AdmissionResponse {
+            uid: uid.to_owned(),
+            ..cached.response.clone()
+        })
+    }
+
+    /// Cache the response for `ttl`. Only the verdicts of the policies are cached: a rejection
+    /// with the 500 code can report a failure of the evaluation, like a Wasm trap, which must
+    /// not be replayed to the next requests
+    pub(crate) fn insert(&self, key: String, response: &AdmissionResponse, ttl: Duration) {
+        if !is_verdict(response) {
+            return;
+        }
+        self.responses
+            .lock()
+            .expect("cannot acquire lock on the evaluation cache")
+            .put(
+                key,
+                CachedResponse {
+                    response: response.clone(),
+                    expires_at: Instant::now() + ttl,
+                },
+            );
+    }
+}
+
+/// Returns `false` when the response can report a failure of the evaluation
+fn is_verdict(response: &AdmissionResponse) -> bool {
+    response.allowed
+        || response
+            .status
+            .as_ref()
+            .is_none_or(|status| status.code != Some(500))
+}
+
+/// Returns the digest identifying a policy, made of its Wasm module and of its settings.
+/// `None` when the settings cannot be serialized: the responses of the policy cannot be
+/// cached, they could be shared with the ones given with other settings.
+pub(crate) fn policy_digest(module_digest: &str, settings: &PolicySettings) -> Option<String> {
+    let settings = serde_json::to_value(settings).ok()?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(module_digest.as_bytes());
+    hash_json(&mut hasher, &settings);
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+/// Returns the key of the response given by the policy to the request. The request is
+/// hashed in a canonical way: the order of the fields of the JSON objects does not matter.
+pub(crate) fn cache_key(
+    policy_id: &PolicyID,
+    policy_digest: &str,
+    request: &AdmissionRequest,
+) -> Option<String> {
+    let mut request = serde_json::to_value(request).ok()?;
+    if let Some(request) = request.as_object_mut() {
+        for field in IGNORED_REQUEST_FIELDS {
+            request.remove(field);
+        }
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(policy_id.to_string().as_bytes());
+    hasher.update(policy_digest.as_bytes());
+    hash_json(&mut hasher, &request);
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+/// Feed the JSON value to the hasher, the keys of the objects are sorted
+fn hash_json(hasher: &mut Sha256, value: &serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            let mut keys: Vec<&String> = object.keys().collect();
+            keys.sort();
+            hasher.update(b"{");
+            for key in keys {
+                hash_json(hasher, &serde_json::Value::String(key.to_owned()));
+                hasher.update(b":");
+                hash_json(hasher, &object[key]);
+                hasher.update(b",");
+            }
+            hasher.update(b"}");
+        }
+        serde_json::Value::Array(array) => {
+            hasher.update(b"[");
+            for item in array {
+                hash_json(hasher, item);
+                hasher.update(b",");
+            }
+            hasher.update(b"]");
+        }
+        scalar => hasher.update(scalar.to_string().as_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::build_admission_review_request;
+    use rstest::*;
+    use serde_json::json;
+
+    fn policy_id() -> PolicyID {
+        PolicyID::Policy("my-policy".to_owned())
+    }
+
+    #[test]
+    fn cache_key_ignores_the_uid_and_the_dry_run() {
+        let request = build_admission_review_request().request;
+        let mut other_request = serde_json::to_value(&request).unwrap();
+        other_request["uid"] = json!("other-uid");
+        other_request["dryRun"] = json!(true);
+        let other_request: AdmissionRequest = serde_json::from_value(other_request).unwrap();
+
+        assert_eq!(
+            cache_key(&policy_id(), "digest", &request),
+            cache_key(&policy_id(), "digest", &other_request)
+        );
+    }
+
+    #[test]
+    fn cache_key_depends_on_the_policy_and_the_request() {
+        let request = build_admission_review_request().request;
+        let mut other_request = request.clone();
+        other_request.operation = "DELETE".to_owned();
+
+        let key = cache_key(&policy_id(), "digest", &request);
+        assert_ne!(key, cache_key(&policy_id(), "digest", &other_request));
+        assert_ne!(key, cache_key(&policy_id(), "other-digest", &request));
+        assert_ne!(
+            key,
+            cache_key(&PolicyID::Policy("other".to_owned()), "digest", &request)
+        );
+    }
+
+    #[test]
+    fn hash_does_not_depend_on_the_order_of_the_fields() {
+        let hash = |value: serde_json::Value| {
+            let mut hasher = Sha256::new();
+            hash_json(&mut hasher, &value);
+            format!("{:x}", hasher.finalize())
+        };
+
+        assert_eq!(
+            hash(json!({"a": 1, "b": {"c": [1, 2], "d": null}})),
+            hash(json!({"b": {"d": null, "c": [1, 2]}, "a": 1}))
+        );
+        assert_ne!(
+            hash(json!({"b": {"c": [1, 2]}})),
+            hash(json!({"b": {"c": [2, 1]}}))
+        );
+    }
+
+    fn allowed_response() -> AdmissionResponse {
+        AdmissionResponse {
+            uid: "uid".to_owned(),
+            allowed: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn least_recently_used_responses_are_evicted() {
+        let cache = EvaluationCache::new(NonZeroUsize::new(2).unwrap());
+        let response = allowed_response();
+        let ttl = Duration::from_secs(60);
+        cache.insert("first".to_owned(), &response, ttl);
+        cache.insert("second".to_owned(), &response, ttl);
+        // Use the first response, the second one becomes the least recently used
+        assert!(cache.get("first", "uid").is_some());
+        cache.insert("third".to_owned(), &response, ttl);
+
+        assert!(cache.get("second", "uid").is_none());
+        assert!(cache.get("third", "uid").is_some());
+        let cached_response = cache.get("first", "other-uid").unwrap();
+        assert_eq!(cached_response.uid, "other-uid");
+        assert!(cached_response.allowed);
+    }
+
+    #[rstest]
+    #[case::allowed(allowed_response(), true)]
+    #[case::rejected(AdmissionResponse::reject("uid".to_owned(), "denied".to_owned(), 400), true)]
+    #[case::failed(AdmissionResponse::reject("uid".to_owned(), "wasm trap".to_owned(), 500), false)]
+    fn only_verdicts_are_cached(#[case] response: AdmissionResponse, #[case] cached: bool) {
+        let cache = EvaluationCache::new(NonZeroUsize::new(2).unwrap());
+        cache.insert("key".to_owned(), &response, Duration::from_secs(60));
+
+        assert_eq!(cache.get("key", "uid").is_some(), cached);
+    }
+
+    #[test]
+    fn expired_responses_are_not_returned() {
+        let cache = EvaluationCache::new(NonZeroUsize::new(2).unwrap());
+        cache.insert("expired".to_owned(), &allowed_response(), Duration::ZERO);
+        cache.insert(
+            "valid".to_owned(),
+            &allowed_response(),
+            Duration::from_secs(60),
+        );
+
+        assert!(cache.get("expired", "uid").is_none());
+        assert!(cache.get("valid", "uid").is_some());
+    }
+
+    #[test]
+    fn policy_digest_depends_on_the_module_and_the_settings() {
+        let settings = PolicySettings::try_from(&json!({"a": 1})).unwrap();
+        let other_settings = PolicySettings::try_from(&json!({"a": 2})).unwrap();
+
+        let digest = policy_digest("module", &settings);
+        assert!(digest.is_some());
+        assert_ne!(digest, policy_digest("other-module", &settings));
+        assert_ne!(digest, policy_digest("module", &other_settings));
+    }
+}
@@ -1,9 +1,12 @@
 use std::{
-    collections::{BTreeSet, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    num::NonZeroUsize,
     sync::Arc,
+    time::Duration,
 };
 
 use policy_evaluator::{
+    admission_request::AdmissionRequest,
     admission_response::AdmissionResponse,
     admission_response_handler::{
         errors::{EvaluationError, Result},
@@ -15,26 +18,37 @@ use policy_evaluator::{
     kubewarden_policy_sdk::settings::SettingsValidationResponse,
     policy_evaluator::{PolicyEvaluator, PolicyEvaluatorPre, PolicyExecutionMode, ValidateRequest},
     policy_evaluator_builder::PolicyEvaluatorBuilder,
-    policy_group_evaluator::{PolicyGroupMemberSettings, evaluator::PolicyGroupEvaluator},
     policy_metadata::ContextAwareResource,
     wasmtime,
 };
 use tokio::sync::mpsc;
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::{
-    config::{PolicyOrPolicyGroup, PolicyOrPolicyGroupSettings},
+    config::{FailurePolicy, OnTimeout, PolicyOrPolicyGroup, PolicyOrPolicyGroupSettings},
     evaluation::{
+        always_accepted_namespaces::AlwaysAcceptedNamespaces,
+        evaluation_cache::{self, EvaluationCache},
+        match_conditions::CompiledMatchConditions,
         policy_evaluation_settings::PolicyEvaluationSettings,
+        policy_group_evaluator::{MemberCost, PolicyGroupEvaluator, PolicyGroupMemberEvaluator},
+        policy_info::{PolicyGroupInfo, PolicyInfo},
+        policy_timeout,
         precompiled_policy::{PrecompiledPolicies, PrecompiledPolicy},
+        validation_response::{FailureKind, ValidationResponse},
     },
+    metrics,
 };
 
 #[cfg(test)]
 use mockall::automock;
 
 /// The digest of a WebAssembly module
-type ModuleDigest = String;
+pub(crate) type ModuleDigest = String;
+
+/// Identifies a `PolicyEvaluatorPre`: the digest of its Wasm module, and the epoch deadline
+/// it has been built with
+pub(crate) type PolicyEvaluatorPreKey = (ModuleDigest, Option<u64>);
 
 /// This structure contains all the policies defined by the user inside of the `policies.yml`.
 /// It also provides helper methods to perform the validation of a request and the validation
@@ -54,20 +68,19 @@ type ModuleDigest = String;
 /// only once, during the bootstrap phase.
 #[derive(Default)]
 pub(crate) struct EvaluationEnvironment {
-    /// The name of the Namespace where Policy Server doesn't operate. All the requests
-    /// involving this Namespace are going to be accepted. This is usually done to prevent user
-    /// policies from messing with the components of the Kubewarden stack (which are all
-    /// deployed inside of the same Namespace).
-    always_accept_admission_reviews_on_namespace: Option<String>,
-
-    /// A map with the module digest as key, and the associated `PolicyEvaluatorPre`
-    /// as value
+    /// The Namespaces where Policy Server doesn't operate. All the requests involving these
+    /// Namespaces are going to be accepted.
+    always_accepted_namespaces: AlwaysAcceptedNamespaces,
+
+    /// A map with the module digest and the epoch deadline as key, and the associated
+    /// `PolicyEvaluatorPre` as value. The epoch deadline is part of the key because it is
+    /// set when the `PolicyEvaluatorPre` is built
     ///
     /// Note: the `PolicyEvaluatorPre` is wrapped into an `Arc` to allow cheap cloning
     /// when it's being used inside of a `GroupPolicyEvaluator`.
     /// We have to use an `Arc` instead of a `Rc` because rhai (used by the `PolicyGroupEvaluator`)
     /// requires `+send` and `+sync`.
-    module_digest_to_policy_evaluator_pre: HashMap<ModuleDigest, Arc<PolicyEvaluatorPre>>,
+    module_digest_to_policy_evaluator_pre: HashMap<PolicyEvaluatorPreKey, Arc<PolicyEvaluatorPre>>,
 
     /// A map with the ID of the policy as value, and the list of ContextAwareResource the
     /// policy is allowed to access.
@@ -77,14 +90,43 @@ pub(crate) struct EvaluationEnvironment {
     /// This allows us to deduplicate the Wasm modules defined by the user.
     policy_id_to_module_digest: HashMap<PolicyID, ModuleDigest>,
 
+    /// Map a `policy_id` to the URL of its Wasm module, as provided by the user.
+    policy_id_to_module_url: HashMap<PolicyID, String>,
+
     /// Map a `policy_id` to the `PolicyEvaluationSettings` instance. This allows us to obtain
     /// the list of settings to be used when evaluating a given policy.
     policy_id_to_settings: HashMap<PolicyID, PolicyEvaluationSettings>,
 
-    /// A map with the policy ID as key, and the error message as value.
+    /// Map a `policy_id` to the conditions a request must satisfy to be evaluated by the
+    /// policy. The policies without conditions are not included.
+    policy_id_to_match_conditions: HashMap<PolicyID, CompiledMatchConditions>,
+
+    /// Map a `policy_id` to what to do when its evaluation fails. The policies without
+    /// failure policy are not included.
+    policy_id_to_failure_policy: HashMap<PolicyID, FailurePolicy>,
+
+    /// Map a `policy_id` to the maximum number of its evaluations running at the same time.
+    /// The policies without `maxConcurrency` are not included.
+    policy_id_to_max_concurrency: HashMap<PolicyID, NonZeroUsize>,
+
+    /// Map the `policy_id` of a policy group member to the cost of its evaluation, measured
+    /// across the evaluations of the group
+    policy_id_to_member_cost: HashMap<PolicyID, Arc<MemberCost>>,
+
+    /// The cache of the responses given by the policies, `None` when the cache is disabled
+    evaluation_cache: Option<Arc<EvaluationCache>>,
+
+    /// Map a `policy_id` to the digest of its Wasm module and of its settings, used to build
+    /// the keys of the evaluation cache, together with the time to live of its responses.
+    /// Only the policies that opted in with `cacheTtlSeconds` are included, the context-aware
+    /// policies never are.
+    policy_id_to_cache_digest: HashMap<PolicyID, (String, Duration)>,
+
+    /// A map with the policy ID as key, and the kind of the error together with its message
+    /// as value.
     /// This is used to store the errors that occurred during policies initialization.
     /// The errors can occur in the fetching of the policy, or in the validation of the settings.
-    policy_initialization_errors: HashMap<PolicyID, String>,
+    policy_initialization_errors: HashMap<PolicyID, (FailureKind, String)>,
 
     /// A Set containing the IDs of the policy groups.
     policy_groups: HashSet<PolicyID>,
@@ -106,7 +148,9 @@ pub(crate) struct EvaluationEnvironmentBuilder<'engine, 'precompiled_policies> {
     callback_handler_tx: mpsc::Sender<CallbackRequest>,
     continue_on_errors: bool,
     global_policy_evaluation_limit_seconds: Option<u64>,
-    always_accept_admission_reviews_on_namespace: Option<String>,
+    always_accepted_namespaces: AlwaysAcceptedNamespaces,
+    policy_evaluators_pre: HashMap<PolicyEvaluatorPreKey, Arc<PolicyEvaluatorPre>>,
+    evaluation_cache: Option<Arc<EvaluationCache>>,
 }
 
 impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'precompiled_policies> {
@@ -122,7 +166,9 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
             callback_handler_tx,
             continue_on_errors: false,
             global_policy_evaluation_limit_seconds: None,
-            always_accept_admission_reviews_on_namespace: None,
+            always_accepted_namespaces: AlwaysAcceptedNamespaces::default(),
+            policy_evaluators_pre: HashMap::new(),
+            evaluation_cache: None,
         }
     }
 
@@ -141,9 +187,29 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
         self
     }
 
-    /// Set the namespace where all the requests are going to be accepted
-    pub fn with_always_accept_admission_reviews_on_namespace(mut self, namespace: String) -> Self {
-        self.always_accept_admission_reviews_on_namespace = Some(namespace);
+    /// Set the namespaces where all the requests are going to be accepted
+    pub fn with_always_accepted_namespaces(
+        mut self,
+        always_accepted_namespaces: AlwaysAcceptedNamespaces,
+    ) -> Self {
+        self.always_accepted_namespaces = always_accepted_namespaces;
+        self
+    }
+
+    /// Reuse the given `PolicyEvaluatorPre` instances, instead of creating them again, for the
+    /// Wasm modules that have the same digest and the same epoch deadline. These are usually
+    /// taken from a previous `EvaluationEnvironment`.
+    pub fn with_policy_evaluators_pre(
+        mut self,
+        policy_evaluators_pre: HashMap<PolicyEvaluatorPreKey, Arc<PolicyEvaluatorPre>>,
+    ) -> Self {
+        self.policy_evaluators_pre = policy_evaluators_pre;
+        self
+    }
+
+    /// Cache the responses of the policies inside of the given cache
+    pub fn with_evaluation_cache(mut self, evaluation_cache: Arc<EvaluationCache>) -> Self {
+        self.evaluation_cache = Some(evaluation_cache);
         self
     }
 
@@ -173,11 +239,10 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
         policies: &HashMap<String, PolicyOrPolicyGroup>,
     ) -> Result<EvaluationEnvironment> {
         let mut eval_env = EvaluationEnvironment {
-            always_accept_admission_reviews_on_namespace: self
-                .always_accept_admission_reviews_on_namespace
-                .clone(),
+            always_accepted_namespaces: self.always_accepted_namespaces.clone(),
             callback_handler_tx: Some(self.callback_handler_tx.clone()),
             global_policy_evaluation_limit_seconds: self.global_policy_evaluation_limit_seconds,
+            evaluation_cache: self.evaluation_cache.clone(),
             ..Default::default()
         };
 
@@ -185,6 +250,34 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
             // there's no way to recover from a parse error, so we just return it
             let id: PolicyID = policy_name.parse()?;
 
+            if let PolicyOrPolicyGroup::Policy { module, .. } = policy {
+                eval_env
+                    .policy_id_to_module_url
+                    .insert(id.clone(), module.to_owned());
+            }
+
+            // The failure policy is registered first: it applies to the initialization
+            // errors too
+            let (PolicyOrPolicyGroup::Policy { failure_policy, .. }
+            | PolicyOrPolicyGroup::PolicyGroup { failure_policy, .. }) = policy;
+            if let Some(failure_policy) = failure_policy {
+                eval_env
+                    .policy_id_to_failure_policy
+                    .insert(id.clone(), *failure_policy);
+            }
+
+            let (PolicyOrPolicyGroup::Policy {
+                max_concurrency, ..
+            }
+            | PolicyOrPolicyGroup::PolicyGroup {
+                max_concurrency, ..
+            }) = policy;
+            if let Some(max_concurrency) = max_concurrency {
+                eval_env
+                    .policy_id_to_max_concurrency
+                    .insert(id.clone(), *max_concurrency);
+            }
+
             let settings = match policy.settings() {
                 Ok(s) => s,
                 Err(e) => {
@@ -195,11 +288,36 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                     }
                     eval_env
                         .policy_initialization_errors
-                        .insert(id.to_owned(), e.to_string());
+                        .insert(id.to_owned(), (FailureKind::Initialization, e.to_string()));
                     continue;
                 }
             };
 
+            let (PolicyOrPolicyGroup::Policy {
+                match_conditions, ..
+            }
+            | PolicyOrPolicyGroup::PolicyGroup {
+                match_conditions, ..
+            }) = policy;
+            if let Some(match_conditions) = match_conditions {
+                match CompiledMatchConditions::new(match_conditions) {
+                    Ok(match_conditions) => {
+                        eval_env
+                            .policy_id_to_match_conditions
+                            .insert(id.clone(), match_conditions);
+                    }
+                    Err(e) => {
+                        if !self.continue_on_errors {
+                            return Err(EvaluationError::BootstrapFailure(format!("{id}: {e}")));
+                        }
+                        eval_env
+                            .policy_initialization_errors
+                            .insert(id.to_owned(), (FailureKind::Initialization, e));
+                        continue;
+                    }
+                }
+            }
+
             match policy {
                 PolicyOrPolicyGroup::Policy {
                     module: url,
@@ -208,6 +326,7 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                     allowed_to_mutate,
                     context_aware_resources,
                     timeout_eval_seconds,
+                    cache_ttl_seconds,
                     ..
                 } => {
                     let policy_evaluation_settings = PolicyEvaluationSettings {
@@ -216,6 +335,7 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                         settings,
                         custom_rejection_message: message.clone(),
                         timeout_eval_seconds: timeout_eval_seconds.to_owned(),
+                        on_timeout: OnTimeout::default(),
                     };
 
                     let epoch_deadline =
@@ -228,7 +348,7 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                         epoch_deadline,
                     };
 
-                    if let Err(e) = self.bootstrap_policy(
+                    if let Err((kind, e)) = self.bootstrap_policy(
                         &mut eval_env,
                         id.clone(),
                         url,
@@ -240,21 +360,33 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                         }
                         eval_env
                             .policy_initialization_errors
-                            .insert(id.to_owned(), e.to_string());
+                            .insert(id.to_owned(), (kind, e.to_string()));
                         continue;
                     }
+
+                    if let Some(cache_ttl_seconds) = cache_ttl_seconds
+                        && self.evaluation_cache.is_some()
+                        && context_aware_resources.is_empty()
+                    {
+                        eval_env.register_cache_digest(
+                            &id,
+                            Duration::from_secs(cache_ttl_seconds.get()),
+                        );
+                    }
                 }
                 PolicyOrPolicyGroup::PolicyGroup {
                     policy_mode,
                     policies,
+                    allowed_to_mutate,
                     ..
                 } => {
                     let policy_evaluation_settings = PolicyEvaluationSettings {
                         policy_mode: policy_mode.to_owned(),
-                        allowed_to_mutate: false, // Group policies are not allowed to mutate
+                        allowed_to_mutate: allowed_to_mutate.unwrap_or(false),
                         custom_rejection_message: None,
                         settings,
                         timeout_eval_seconds: None,
+                        on_timeout: OnTimeout::default(),
                     };
                     eval_env.register_policy_group(&id, policy_evaluation_settings);
 
@@ -263,6 +395,9 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                             group: id.to_string(),
                             name: policy_name.clone(),
                         };
+                        eval_env
+                            .policy_id_to_module_url
+                            .insert(policy_id.clone(), policy.module.clone());
                         let settings = match policy.settings() {
                             Ok(s) => s,
                             Err(e) => {
@@ -271,19 +406,21 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                                         "cannot extract settings from policy: {e}"
                                     )));
                                 }
-                                eval_env
-                                    .policy_initialization_errors
-                                    .insert(policy_id, e.to_string());
+                                eval_env.policy_initialization_errors.insert(
+                                    policy_id,
+                                    (FailureKind::Initialization, e.to_string()),
+                                );
                                 continue;
                             }
                         };
 
                         let policy_evaluation_settings = PolicyEvaluationSettings {
-                            policy_mode: PolicyMode::Protect,
+                            policy_mode: policy.policy_mode.clone(),
                             allowed_to_mutate: false,
                             settings,
                             custom_rejection_message: None,
                             timeout_eval_seconds: policy.timeout_eval_seconds,
+                            on_timeout: policy.on_timeout,
                         };
 
                         let epoch_deadline = policy
@@ -299,7 +436,7 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                             epoch_deadline,
                         };
 
-                        if let Err(e) = self.bootstrap_policy(
+                        if let Err((kind, e)) = self.bootstrap_policy(
                             &mut eval_env,
                             policy_id.clone(),
                             &policy.module,
@@ -311,9 +448,12 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                             }
                             eval_env
                                 .policy_initialization_errors
-                                .insert(policy_id, e.to_string());
+                                .insert(policy_id, (kind, e.to_string()));
                             continue;
                         }
+                        eval_env
+                            .policy_id_to_member_cost
+                            .insert(policy_id, Arc::default());
                     }
                 }
             }
@@ -324,6 +464,9 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
 
     /// Internal method used to bootstrap a policy. The policy is either a single policy or a
     /// children of a policy group.
+    ///
+    /// The error is returned together with its kind: the settings of the policy can be
+    /// invalid, or the policy cannot be initialized at all.
     fn bootstrap_policy(
         &self,
         eval_env: &mut EvaluationEnvironment,
@@ -331,15 +474,29 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
         url: &str,
         policy_evaluation_settings: PolicyEvaluationSettings,
         eval_ctx: EvaluationContext,
-    ) -> Result<()> {
+    ) -> std::result::Result<(), (FailureKind, EvaluationError)> {
         let precompiled_policy = self
             .precompiled_policies
             .get(url)
             .ok_or_else(|| {
                 EvaluationError::BootstrapFailure(format!("cannot find precompiled policy of {id}"))
-            })?
-            .as_ref()
-            .map_err(|e| EvaluationError::BootstrapFailure(format!("{id}: {e}")))?;
+            })
+            .and_then(|precompiled_policy| {
+                precompiled_policy
+                    .as_ref()
+                    .map_err(|e| EvaluationError::BootstrapFailure(format!("{id}: {e}")))
+            })
+            .map_err(|e| (FailureKind::Initialization, e))?;
+
+        let policy_evaluator_pre_key = (precompiled_policy.digest.clone(), eval_ctx.epoch_deadline);
+        if let Some(policy_evaluator_pre) =
+            self.policy_evaluators_pre.get(&policy_evaluator_pre_key)
+        {
+            eval_env
+                .module_digest_to_policy_evaluator_pre
+                .entry(policy_evaluator_pre_key)
+                .or_insert_with(|| policy_evaluator_pre.clone());
+        }
 
         eval_env
             .register(
@@ -349,7 +506,12 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                 eval_ctx,
                 precompiled_policy,
             )
-            .map_err(|e| EvaluationError::BootstrapFailure(e.to_string()))?;
+            .map_err(|e| {
+                (
+                    FailureKind::Initialization,
+                    EvaluationError::BootstrapFailure(e.to_string()),
+                )
+            })?;
 
         eval_env.validate_settings(&id)
     }
@@ -358,13 +520,61 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
 #[cfg_attr(test, automock)]
 #[cfg_attr(test, allow(dead_code))]
 impl EvaluationEnvironment {
-    /// Returns `true` if the given `namespace` is the special Namespace that is ignored by all
-    /// the policies
+    /// Returns `true` if the given `namespace` is one of the special Namespaces that are ignored
+    /// by all the policies
     pub(crate) fn should_always_accept_requests_made_inside_of_namespace(
         &self,
         namespace: &str,
     ) -> bool {
-        self.always_accept_admission_reviews_on_namespace.as_deref() == Some(namespace)
+        self.always_accepted_namespaces.contains(namespace)
+    }
+
+    /// Returns `false` when the request does not satisfy the match conditions of the policy,
+    /// meaning the policy does not have to be evaluated.
+    ///
+    /// The policy is evaluated when its match conditions cannot be checked, or when it could
+    /// not be initialized: the evaluation then reports the initialization error.
+    pub(crate) fn request_matches_conditions(
+        &self,
+        policy_id: &PolicyID,
+        request: &AdmissionRequest,
+    ) -> bool {
+        if self.policy_initialization_errors.contains_key(policy_id) {
+            return true;
+        }
+        let Some(match_conditions) = self.policy_id_to_match_conditions.get(policy_id) else {
+            return true;
+        };
+
+        match_conditions.matches(request).unwrap_or_else(|e| {
+            warn!(%policy_id, error = e, "cannot check the match conditions, evaluating the policy");
+            true
+        })
+    }
+
+    /// Compute the digest used to cache the responses of the given policy, made of the digests
+    /// of its Wasm module and of its settings. The responses are cached for `ttl`.
+    fn register_cache_digest(&mut self, policy_id: &PolicyID, ttl: Duration) {
+        let (Some(module_digest), Some(policy_evaluation_settings)) = (
+            self.policy_id_to_module_digest.get(policy_id),
+            self.policy_id_to_settings.get(policy_id),
+        ) else {
+            return;
+        };
+        let PolicyOrPolicyGroupSettings::Policy(settings) = &policy_evaluation_settings.settings
+        else {
+            return;
+        };
+
+        let Some(cache_digest) = evaluation_cache::policy_digest(module_digest, settings) else {
+            warn!(
+                %policy_id,
+                "cannot compute the digest of the settings, the responses are not cached"
+            );
+            return;
+        };
+        self.policy_id_to_cache_digest
+            .insert(policy_id.to_owned(), (cache_digest, ttl));
     }
 
     /// Register a new policy. It takes care of creating a new `PolicyEvaluator` (when needed).
@@ -390,10 +600,11 @@ impl EvaluationEnvironment {
         precompiled_policy: &PrecompiledPolicy,
     ) -> Result<()> {
         let module_digest = &precompiled_policy.digest;
+        let policy_evaluator_pre_key = (module_digest.to_owned(), eval_ctx.epoch_deadline);
 
         if !self
             .module_digest_to_policy_evaluator_pre
-            .contains_key(module_digest)
+            .contains_key(&policy_evaluator_pre_key)
         {
             debug!(?policy_id, "create wasmtime::Module");
             let module = create_wasmtime_module(policy_id, engine, precompiled_policy)?;
@@ -406,7 +617,7 @@ impl EvaluationEnvironment {
             )?;
 
             self.module_digest_to_policy_evaluator_pre
-                .insert(module_digest.to_owned(), Arc::new(pol_eval_pre));
+                .insert(policy_evaluator_pre_key, Arc::new(pol_eval_pre));
         }
 
         self.policy_id_to_module_digest
@@ -423,6 +634,14 @@ impl EvaluationEnvironment {
         Ok(())
     }
 
+    /// Returns the `PolicyEvaluatorPre` instances in use, indexed by the digest of their
+    /// Wasm module and by their epoch deadline
+    pub(crate) fn policy_evaluators_pre(
+        &self,
+    ) -> HashMap<PolicyEvaluatorPreKey, Arc<PolicyEvaluatorPre>> {
+        self.module_digest_to_policy_evaluator_pre.clone()
+    }
+
     /// Register a policy group
     fn register_policy_group(
         &mut self,
@@ -461,6 +680,138 @@ impl EvaluationEnvironment {
             .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))
     }
 
+    /// Given a policy ID, returns what to do when its evaluation fails. `None` when the
+    /// policy does not define a failure policy
+    pub(crate) fn get_policy_failure_policy(&self, policy_id: &PolicyID) -> Option<FailurePolicy> {
+        self.policy_id_to_failure_policy.get(policy_id).copied()
+    }
+
+    /// Returns the maximum number of evaluations running at the same time of each policy,
+    /// indexed by policy ID. The policies whose concurrency is not limited are not included
+    pub(crate) fn policies_max_concurrency(&self) -> HashMap<String, NonZeroUsize> {
+        self.policy_id_to_max_concurrency
+            .iter()
+            .map(|(policy_id, max_concurrency)| (policy_id.to_string(), *max_concurrency))
+            .collect()
+    }
+
+    /// Returns the errors that occurred while initializing the policies, indexed by policy ID
+    pub(crate) fn policy_initialization_errors(&self) -> BTreeMap<String, String> {
+        self.policy_initialization_errors
+            .iter()
+            .map(|(policy_id, (_, error))| (policy_id.to_string(), error.to_owned()))
+            .collect()
+    }
+
+    /// Validate the settings of all the policies, including the policy groups and their members.
+    /// Returns the validation error of each policy, indexed by policy ID. The policies that
+    /// could not be initialized are reported with their initialization error.
+    pub(crate) fn validate_all_settings(&self) -> BTreeMap<String, Option<String>> {
+        self.policy_id_to_settings
+            .keys()
+            .chain(self.policy_initialization_errors.keys())
+            .map(|policy_id| {
+                let error = match self.policy_initialization_errors.get(policy_id) {
+                    Some((_, error)) => Some(error.to_owned()),
+                    None => self
+                        .validate_settings(policy_id)
+                        .err()
+                        .map(|(_, e)| e.to_string()),
+                };
+                (policy_id.to_string(), error)
+            })
+            .collect()
+    }
+
+    /// Returns the details of all the policies defined by the user, sorted by their ID.
+    /// The members of the policy groups are reported inside of their group.
+    pub(crate) fn list_policies(&self) -> Vec<PolicyInfo> {
+        self.top_level_policy_ids()
+            .into_iter()
+            .filter_map(|policy_id| self.get_policy_info(&policy_id).ok())
+            .collect()
+    }
+
+    /// Returns the IDs of all the policies defined by the user, sorted alphabetically.
+    /// The members of the policy groups are not included.
+    pub(crate) fn policy_ids(&self) -> Vec<String> {
+        self.top_level_policy_ids()
+            .into_iter()
+            .map(|policy_id| policy_id.to_string())
+            .collect()
+    }
+
+    /// Given a policy ID, returns its details and the errors that occurred while loading it
+    pub(crate) fn get_policy_info(&self, policy_id: &PolicyID) -> Result<PolicyInfo> {
+        let settings = self.policy_id_to_settings.get(policy_id);
+        let initialization_error = self
+            .policy_initialization_errors
+            .get(policy_id)
+            .map(|(_, error)| error.clone());
+        if settings.is_none() && initialization_error.is_none() {
+            return Err(EvaluationError::PolicyNotFound(policy_id.to_string()));
+        }
+
+        let group = match settings.map(|settings| &settings.settings) {
+            Some(PolicyOrPolicyGroupSettings::PolicyGroup {
+                expression,
+                message,
+                policies,
+            }) => {
+                let mut members = policies
+                    .iter()
+                    .map(|name| {
+                        self.get_policy_info(&PolicyID::PolicyGroupPolicy {
+                            group: policy_id.to_string(),
+                            name: name.to_owned(),
+                        })
+                    })
+                    .collect::<Result<Vec<PolicyInfo>>>()?;
+                members.sort_by(|a, b| a.id.cmp(&b.id));
+
+                Some(PolicyGroupInfo {
+                    expression: expression.to_owned(),
+                    message: message.to_owned(),
+                    members,
+                })
+            }
+            _ => None,
+        };
+
+        Ok(PolicyInfo {
+            id: policy_id.to_string(),
+            module: self.policy_id_to_module_url.get(policy_id).cloned(),
+            module_digest: self.policy_id_to_module_digest.get(policy_id).cloned(),
+            policy_mode: settings.map(|settings| settings.policy_mode.clone().into()),
+            allowed_to_mutate: settings.map(|settings| settings.allowed_to_mutate),
+            context_aware_resources: self
+                .policy_id_to_ctx_aware_allowed_resources
+                .get(policy_id)
+                .cloned()
+                .unwrap_or_default(),
+            timeout_eval_seconds: settings
+                .and_then(|settings| settings.timeout_eval_seconds)
+                .or(self.global_policy_evaluation_limit_seconds),
+            group,
+            initialization_error,
+        })
+    }
+
+    fn top_level_policy_ids(&self) -> Vec<PolicyID> {
+        let mut policy_ids: Vec<PolicyID> = self
+            .policy_id_to_settings
+            .keys()
+            .chain(self.policy_initialization_errors.keys())
+            .filter(|policy_id| matches!(policy_id, PolicyID::Policy(_)))
+            .cloned()
+            .collect::<HashSet<PolicyID>>()
+            .into_iter()
+            .collect();
+        policy_ids.sort_by_key(|policy_id| policy_id.to_string());
+
+        policy_ids
+    }
+
     /// Given a policy ID, returns the settings provided by the user inside of `policies.yml`
     fn get_policy_settings(&self, policy_id: &PolicyID) -> Result<PolicyEvaluationSettings> {
         let settings = self
@@ -471,13 +822,21 @@ impl EvaluationEnvironment {
         Ok(settings)
     }
 
-    /// Validate the settings the user provided for the given policy
-    fn validate_settings(&mut self, policy_id: &PolicyID) -> Result<()> {
-        let settings = self.get_policy_settings(policy_id)?;
+    /// Validate the settings the user provided for the given policy. The error is returned
+    /// together with its kind: the settings are invalid, or they cannot be validated.
+    fn validate_settings(
+        &self,
+        policy_id: &PolicyID,
+    ) -> std::result::Result<(), (FailureKind, EvaluationError)> {
+        let settings = self
+            .get_policy_settings(policy_id)
+            .map_err(|e| (FailureKind::Initialization, e))?;
 
         match &settings.settings {
             PolicyOrPolicyGroupSettings::Policy(settings) => {
-                let mut evaluator = self.rehydrate(policy_id)?;
+                let mut evaluator = self
+                    .rehydrate(policy_id)
+                    .map_err(|e| (FailureKind::Initialization, e))?;
                 match evaluator.validate_settings(settings) {
                     SettingsValidationResponse {
                         valid: true,
@@ -492,20 +851,23 @@ impl EvaluationEnvironment {
                             message.unwrap_or("no message".to_owned())
                         );
 
-                        return Err(EvaluationError::PolicyInitialization(error_message));
+                        return Err((
+                            FailureKind::SettingsInvalid,
+                            EvaluationError::PolicyInitialization(error_message),
+                        ));
                     }
                 };
             }
             PolicyOrPolicyGroupSettings::PolicyGroup { .. } => {
-                let group_evaluator = self.build_policy_group_evaluator(policy_id)?;
-                let validation_result = group_evaluator.validate_settings();
-                if !validation_result.valid {
-                    return Err(EvaluationError::PolicyInitialization(
-                        validation_result
-                            .message
-                            .unwrap_or(format!("{} settings are not valid", policy_id)),
-                    ));
-                }
+                let group_evaluator = self
+                    .build_policy_group_evaluator(policy_id)
+                    .map_err(|e| (FailureKind::Initialization, e))?;
+                group_evaluator.validate_settings().map_err(|e| {
+                    (
+                        FailureKind::SettingsInvalid,
+                        EvaluationError::PolicyInitialization(e),
+                    )
+                })?;
             }
         }
 
@@ -514,6 +876,19 @@ impl EvaluationEnvironment {
 
     /// Internal method, create a `PolicyEvaluator` by using a pre-initialized instance
     fn rehydrate(&self, policy_id: &PolicyID) -> Result<PolicyEvaluator> {
+        let (policy_evaluator_pre, eval_ctx) = self.policy_evaluator_pre(policy_id)?;
+
+        policy_evaluator_pre.rehydrate(&eval_ctx).map_err(|e| {
+            EvaluationError::WebAssemblyError(format!("cannot rehydrate PolicyEvaluatorPre: {e}"))
+        })
+    }
+
+    /// Internal method, returns the pre-initialized instance of the given policy, together
+    /// with the context required to rehydrate it
+    fn policy_evaluator_pre(
+        &self,
+        policy_id: &PolicyID,
+    ) -> Result<(Arc<PolicyEvaluatorPre>, EvaluationContext)> {
         if self.policy_groups.contains(policy_id) {
             return Err(EvaluationError::CannotRehydratePolicyGroup(
                 policy_id.to_string(),
@@ -533,7 +908,7 @@ impl EvaluationEnvironment {
 
         let policy_evaluator_pre = self
             .module_digest_to_policy_evaluator_pre
-            .get(module_digest)
+            .get(&(module_digest.to_owned(), epoch_deadline))
             .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))?;
 
         let ctx_aware_resources_allow_list = self
@@ -548,19 +923,24 @@ impl EvaluationEnvironment {
             epoch_deadline,
         };
 
-        policy_evaluator_pre.rehydrate(&eval_ctx).map_err(|e| {
-            EvaluationError::WebAssemblyError(format!("cannot rehydrate PolicyEvaluatorPre: {e}"))
-        })
+        Ok((policy_evaluator_pre.clone(), eval_ctx))
     }
 
-    /// Perform a request validation
+    /// Perform a request validation.
+    ///
+    /// When `trace` is set, the policy groups report the results of their members inside
+    /// of the audit annotations of the response, even if their tracing is not enabled.
+    ///
+    /// The failures of the evaluation are reported inside of the response, together with
+    /// their kind. An error is returned only when the policy is not known.
     pub fn validate(
         &self,
         policy_id: &PolicyID,
         req: &ValidateRequest,
-    ) -> Result<AdmissionResponse> {
+        trace: bool,
+    ) -> Result<ValidationResponse> {
         if self.policy_groups.contains(policy_id) {
-            self.validate_policy_group(policy_id, req)
+            self.validate_policy_group(policy_id, req, trace)
         } else {
             self.validate_policy(policy_id, req)
         }
@@ -570,24 +950,85 @@ impl EvaluationEnvironment {
     ///
     /// Note, `self` is wrapped inside of `Arc` because this method is called from within a Rhai engine closure that
     /// requires `+send` and `+sync`.
+    ///
+    /// A 500 rejection is a failure only when the evaluation timed out. The other ones cannot
+    /// be told apart from a rejection made on purpose by the policy, they are verdicts.
     fn validate_policy(
         &self,
         policy_id: &PolicyID,
         req: &ValidateRequest,
-    ) -> Result<AdmissionResponse> {
+    ) -> Result<ValidationResponse> {
         debug!(?policy_id, "validate individual policy");
 
-        if let Some(error) = self.policy_initialization_errors.get(policy_id) {
-            return Err(EvaluationError::PolicyInitialization(error.to_string()));
+        if let Some((kind, error)) = self.policy_initialization_errors.get(policy_id) {
+            return Ok(ValidationResponse::failure(
+                req.uid().to_owned(),
+                *kind,
+                error.to_owned(),
+            ));
+        }
+
+        let cache_key = self.cache_key(policy_id, req);
+        if let Some((evaluation_cache, key, _)) = &cache_key {
+            let cached_response = evaluation_cache.get(key, req.uid());
+            metrics::add_evaluation_cache_request(&metrics::EvaluationCacheRequest {
+                policy_name: policy_id.to_string(),
+                hit: cached_response.is_some(),
+            });
+            if let Some(response) = cached_response {
+                debug!(?policy_id, "response taken from the evaluation cache");
+                return Ok(ValidationResponse::verdict(response));
+            }
         }
 
+        let (policy_evaluator_pre, eval_ctx) = self.policy_evaluator_pre(policy_id)?;
         let settings = match self.get_policy_settings(policy_id)?.settings {
             PolicyOrPolicyGroupSettings::Policy(settings) => settings,
             _ => unreachable!(),
         };
-        let mut evaluator = self.rehydrate(policy_id)?;
+        let start_epoch = policy_timeout::current_epoch();
+        let mut evaluator = match policy_evaluator_pre.rehydrate(&eval_ctx) {
+            Ok(evaluator) => evaluator,
+            Err(e) => {
+                return Ok(ValidationResponse::failure(
+                    req.uid().to_owned(),
+                    FailureKind::WebAssembly,
+                    format!("cannot rehydrate PolicyEvaluatorPre: {e}"),
+                ));
+            }
+        };
+        let response = evaluator.validate(req.clone(), &settings);
+
+        if policy_timeout::is_timeout(&response, start_epoch, eval_ctx.epoch_deadline) {
+            // The timeouts are not cached: the next evaluation could succeed
+            return Ok(ValidationResponse {
+                response,
+                failure: Some(FailureKind::Timeout),
+            });
+        }
+        if let Some((evaluation_cache, key, ttl)) = cache_key {
+            evaluation_cache.insert(key, &response, ttl);
+        }
+
+        Ok(ValidationResponse::verdict(response))
+    }
+
+    /// Returns the evaluation cache together with the key of the response of the policy
+    /// to the request, and the time to live of the response. `None` when the response cannot
+    /// be cached.
+    fn cache_key(
+        &self,
+        policy_id: &PolicyID,
+        req: &ValidateRequest,
+    ) -> Option<(Arc<EvaluationCache>, String, Duration)> {
+        let evaluation_cache = self.evaluation_cache.as_ref()?;
+        let (cache_digest, ttl) = self.policy_id_to_cache_digest.get(policy_id)?;
+        let ValidateRequest::AdmissionRequest(request) = req else {
+            return None;
+        };
 
-        Ok(evaluator.validate(req.clone(), &settings))
+        evaluation_cache::cache_key(policy_id, cache_digest, request)
+            .map(|key| (evaluation_cache.clone(), key, *ttl))
     }
 
     /// Validate a policy group
@@ -598,68 +1039,64 @@ impl EvaluationEnvironment {
         &self,
         policy_id: &PolicyID,
         req: &ValidateRequest,
-    ) -> Result<AdmissionResponse> {
+        trace: bool,
+    ) -> Result<ValidationResponse> {
         let group_evaluator = Arc::new(self.build_policy_group_evaluator(policy_id)?);
-        Ok(group_evaluator.validate(req))
+        Ok(group_evaluator.validate(req, trace))
     }
 
     fn build_policy_group_evaluator(&self, policy_id: &PolicyID) -> Result<PolicyGroupEvaluator> {
-        let (expression, message, policies) = match self.get_policy_settings(policy_id)?.settings {
-            PolicyOrPolicyGroupSettings::PolicyGroup {
-                expression,
-                message,
-                policies,
-            } => (expression, message, policies),
-            _ => unreachable!(),
+        let PolicyOrPolicyGroupSettings::PolicyGroup {
+            expression,
+            expression_language,
+            members_evaluation,
+            mutators,
+            trace,
+            message,
+            policies,
+        } = self.get_policy_settings(policy_id)?.settings
+        else {
+            unreachable!()
         };
 
         let mut evaluator = PolicyGroupEvaluator::new(
             &policy_id.to_string(),
             &message,
             &expression,
-            self.callback_handler_tx.clone(),
-        );
+            expression_language,
+            members_evaluation,
+        )
+        .with_mutators(mutators)
+        .with_trace(trace);
 
         for sub_policy_name in policies {
             let policy_id = PolicyID::PolicyGroupPolicy {
                 group: policy_id.to_string(),
                 name: sub_policy_name.clone(),
             };
-            let module_digest = self
-                .policy_id_to_module_digest
-                .get(&policy_id)
-                .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))?;
-
-            let policy_evaluator_pre = self
-                .module_digest_to_policy_evaluator_pre
-                .get(module_digest)
-                .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))?;
-
-            let ctx_aware_resources_allow_list = self
-                .policy_id_to_ctx_aware_allowed_resources
-                .get(&policy_id)
-                .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))?;
+            let (policy_evaluator_pre, eval_ctx) = self.policy_evaluator_pre(&policy_id)?;
 
             let policy_settings = self.get_policy_settings(&policy_id)?;
             let settings = match policy_settings.settings {
                 PolicyOrPolicyGroupSettings::Policy(settings) => settings,
                 _ => unreachable!(),
             };
-
-            let epoch_deadline = policy_settings
-                .timeout_eval_seconds
-                .or(self.global_policy_evaluation_limit_seconds);
-
-            let policy_group_member_settings = PolicyGroupMemberSettings {
-                settings,
-                ctx_aware_resources_allow_list: ctx_aware_resources_allow_list.clone(),
-                epoch_deadline,
-            };
+            let cost = self
+                .policy_id_to_member_cost
+                .get(&policy_id)
+                .cloned()
+                .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))?;
 
             evaluator.add_policy_member(
                 &sub_policy_name,
-                policy_evaluator_pre.clone(),
-                policy_group_member_settings,
+                PolicyGroupMemberEvaluator {
+                    policy_evaluator_pre,
+                    eval_ctx,
+                    settings,
+                    policy_mode: policy_settings.policy_mode,
+                    on_timeout: policy_settings.on_timeout,
+                    cost,
+                },
             );
         }
 
@@ -708,14 +1145,20 @@ fn create_policy_evaluator_pre(
 
 #[cfg(test)]
 mod tests {
-    use std::collections::BTreeSet;
+    use std::{
+        collections::BTreeSet,
+        num::{NonZeroU64, NonZeroUsize},
+    };
 
     use policy_evaluator::{admission_response, policy_evaluator::ValidateRequest};
     use rstest::*;
     use sha2::{Digest, Sha256};
 
     use super::*;
-    use crate::config::{PolicyGroupMember, PolicyOrPolicyGroup};
+    use crate::config::{
+        ExpressionLanguage, FailurePolicy, MembersEvaluation, OnTimeout, PolicyGroupMember,
+        PolicyOrPolicyGroup,
+    };
     use crate::test_utils::build_admission_review_request;
 
     /// build a precompiled policy of the given wasm module. Assumes this is a OPA Gatekeeper policy
@@ -783,6 +1226,10 @@ mod tests {
                     context_aware_resources: BTreeSet::new(),
                     message: None,
                     timeout_eval_seconds: None,
+                    failure_policy: None,
+                    max_concurrency: None,
+                    cache_ttl_seconds: None,
+                    match_conditions: None,
                 },
             );
             precompiled_policies.insert(policy_url, Ok(precompiled_policy.clone()));
@@ -799,6 +1246,10 @@ mod tests {
                 context_aware_resources: BTreeSet::new(),
                 message: None,
                 timeout_eval_seconds: Some(5),
+                failure_policy: Some(FailurePolicy::Ignore),
+                max_concurrency: NonZeroUsize::new(2),
+                cache_ttl_seconds: None,
+                match_conditions: None,
             },
         );
 
@@ -814,12 +1265,22 @@ mod tests {
                         settings: None,
                         context_aware_resources: BTreeSet::new(),
                         timeout_eval_seconds: None,
+                        policy_mode: PolicyMode::Protect,
+                        on_timeout: OnTimeout::Error,
                     },
                 )]
                 .into_iter()
                 .collect(),
                 expression: "true || happy_policy_1()".to_string(),
+                expression_language: ExpressionLanguage::Rhai,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
                 message: "something went wrong".to_string(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
             },
         );
         policies.insert(
@@ -827,8 +1288,16 @@ mod tests {
             PolicyOrPolicyGroup::PolicyGroup {
                 policy_mode: PolicyMode::Protect,
                 expression: "2 > 1".to_string(),
+                expression_language: ExpressionLanguage::Rhai,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
                 message: "something went wrong".to_string(),
                 policies: HashMap::new(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
             },
         );
         policies.insert(
@@ -842,12 +1311,22 @@ mod tests {
                         settings: None,
                         context_aware_resources: BTreeSet::new(),
                         timeout_eval_seconds: None,
+                        policy_mode: PolicyMode::Protect,
+                        on_timeout: OnTimeout::Error,
                     },
                 )]
                 .into_iter()
                 .collect(),
                 expression: "unknown_policy() || happy_policy_1()".to_string(),
+                expression_language: ExpressionLanguage::Rhai,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
                 message: "something went wrong".to_string(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
             },
         );
         policies.insert(
@@ -855,8 +1334,16 @@ mod tests {
             PolicyOrPolicyGroup::PolicyGroup {
                 policy_mode: PolicyMode::Protect,
                 expression: "something that doesn't make sense".to_string(),
+                expression_language: ExpressionLanguage::Rhai,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
                 message: "something went wrong".to_string(),
                 policies: HashMap::new(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
             },
         );
         policies.insert(
@@ -864,8 +1351,16 @@ mod tests {
             PolicyOrPolicyGroup::PolicyGroup {
                 policy_mode: PolicyMode::Protect,
                 expression: "1 + 1".to_string(),
+                expression_language: ExpressionLanguage::Rhai,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
                 message: "something went wrong".to_string(),
                 policies: HashMap::new(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
             },
         );
         policies.insert(
@@ -880,12 +1375,22 @@ mod tests {
                         settings: None,
                         context_aware_resources: BTreeSet::new(),
                         timeout_eval_seconds: None,
+                        policy_mode: PolicyMode::Protect,
+                        on_timeout: OnTimeout::Error,
                     },
                 )]
                 .into_iter()
                 .collect(),
                 expression: "happy_policy_1() + 1".to_string(),
+                expression_language: ExpressionLanguage::Rhai,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
                 message: "something went wrong".to_string(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
             },
         );
         policies.insert(
@@ -900,6 +1405,63 @@ mod tests {
                             settings: None,
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
+                        },
+                    ),
+                    (
+                        "unhappy_policy_1".to_string(),
+                        PolicyGroupMember {
+                            module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
+                        },
+                    ),
+                    (
+                        "unhappy_policy_2".to_string(),
+                        PolicyGroupMember {
+                            module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                expression: "unhappy_policy_1() || (happy_policy_1() && unhappy_policy_2())"
+                    .to_string(),
+                expression_language: ExpressionLanguage::Rhai,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
+                message: "something went wrong".to_string(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
+            },
+        );
+
+        policies.insert(
+            "group_policy_parallel_with_unhappy_or_bracket_happy_and_unhappy_bracket".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                policies: vec![
+                    (
+                        "happy_policy_1".to_string(),
+                        PolicyGroupMember {
+                            module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
                         },
                     ),
                     (
@@ -909,6 +1471,8 @@ mod tests {
                             settings: None,
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
                         },
                     ),
                     (
@@ -918,6 +1482,8 @@ mod tests {
                             settings: None,
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
                         },
                     ),
                 ]
@@ -925,7 +1491,15 @@ mod tests {
                 .collect(),
                 expression: "unhappy_policy_1() || (happy_policy_1() && unhappy_policy_2())"
                     .to_string(),
+                expression_language: ExpressionLanguage::Rhai,
+                members_evaluation: MembersEvaluation::Parallel,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
                 message: "something went wrong".to_string(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
             },
         );
 
@@ -941,6 +1515,8 @@ mod tests {
                             settings: None,
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
                         },
                     ),
                     (
@@ -950,6 +1526,8 @@ mod tests {
                             settings: None,
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
                         },
                     ),
                     (
@@ -959,6 +1537,8 @@ mod tests {
                             settings: None,
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
                         },
                     ),
                 ]
@@ -966,22 +1546,290 @@ mod tests {
                 .collect(),
                 expression: "unhappy_policy_1() || happy_policy_1() || unhappy_policy_2()"
                     .to_string(),
+                expression_language: ExpressionLanguage::Rhai,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
                 message: "something went wrong".to_string(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
             },
         );
 
-        let eval_env_builder =
-            EvaluationEnvironmentBuilder::new(&engine, &precompiled_policies, callback_handler_tx);
-        eval_env_builder
-            .build_evaluation_environment(&policies)
-            .unwrap()
-    }
+        policies.insert(
+            "group_policy_cel_happy_and_update".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                policies: vec![
+                    (
+                        "happy_policy_1".to_string(),
+                        PolicyGroupMember {
+                            module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
+                        },
+                    ),
+                    (
+                        "unhappy_policy_1".to_string(),
+                        PolicyGroupMember {
+                            module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                expression:
+                    r#"unhappy_policy_1 || (happy_policy_1 && request.operation == "UPDATE")"#
+                        .to_string(),
+                expression_language: ExpressionLanguage::Cel,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
+                message: "something went wrong".to_string(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
+            },
+        );
 
-    #[rstest]
-    #[case::policy_not_defined("policy_not_defined", true)]
-    #[case::policy_known("happy_policy_1", false)]
-    fn lookup_policy(#[case] policy_id: &str, #[case] expect_error: bool) {
-        let policy_id = PolicyID::Policy(policy_id.to_string());
+        policies.insert(
+            "group_policy_cel_happy_and_create".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                policies: vec![
+                    (
+                        "happy_policy_1".to_string(),
+                        PolicyGroupMember {
+                            module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
+                        },
+                    ),
+                    (
+                        "unhappy_policy_1".to_string(),
+                        PolicyGroupMember {
+                            module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                expression: r#"happy_policy_1 && request.operation == "CREATE""#.to_string(),
+                expression_language: ExpressionLanguage::Cel,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
+                message: "something went wrong".to_string(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
+            },
+        );
+
+        policies.insert(
+            "group_policy_cel_unhappy".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                policies: vec![
+                    (
+                        "happy_policy_1".to_string(),
+                        PolicyGroupMember {
+                            module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
+                        },
+                    ),
+                    (
+                        "unhappy_policy_1".to_string(),
+                        PolicyGroupMember {
+                            module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                expression: "unhappy_policy_1".to_string(),
+                expression_language: ExpressionLanguage::Cel,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
+                message: "something went wrong".to_string(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
+            },
+        );
+
+        policies.insert(
+            "group_policy_cel_not_valid_expression_because_of_unknown_policy".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                policies: vec![
+                    (
+                        "happy_policy_1".to_string(),
+                        PolicyGroupMember {
+                            module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
+                        },
+                    ),
+                    (
+                        "unhappy_policy_1".to_string(),
+                        PolicyGroupMember {
+                            module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                expression: "happy_policy_1 || unknown_policy".to_string(),
+                expression_language: ExpressionLanguage::Cel,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
+                message: "something went wrong".to_string(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
+            },
+        );
+
+        policies.insert(
+            "group_policy_cel_not_valid_expression_because_of_typos".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                policies: vec![
+                    (
+                        "happy_policy_1".to_string(),
+                        PolicyGroupMember {
+                            module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
+                        },
+                    ),
+                    (
+                        "unhappy_policy_1".to_string(),
+                        PolicyGroupMember {
+                            module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                expression: "happy_policy_1 ||".to_string(),
+                expression_language: ExpressionLanguage::Cel,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
+                message: "something went wrong".to_string(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
+            },
+        );
+
+        policies.insert(
+            "group_policy_with_happy_and_monitored_unhappy".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                policies: vec![
+                    (
+                        "happy_policy_1".to_string(),
+                        PolicyGroupMember {
+                            module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Protect,
+                            on_timeout: OnTimeout::Error,
+                        },
+                    ),
+                    (
+                        "unhappy_policy_1".to_string(),
+                        PolicyGroupMember {
+                            module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            policy_mode: PolicyMode::Monitor,
+                            on_timeout: OnTimeout::Error,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                expression: "happy_policy_1() && unhappy_policy_1()".to_string(),
+                expression_language: ExpressionLanguage::Rhai,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
+                message: "something went wrong".to_string(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
+            },
+        );
+
+        let eval_env_builder =
+            EvaluationEnvironmentBuilder::new(&engine, &precompiled_policies, callback_handler_tx);
+        eval_env_builder
+            .build_evaluation_environment(&policies)
+            .unwrap()
+    }
+
+    #[rstest]
+    #[case::policy_not_defined("policy_not_defined", true)]
+    #[case::policy_known("happy_policy_1", false)]
+    fn lookup_policy(#[case] policy_id: &str, #[case] expect_error: bool) {
+        let policy_id = PolicyID::Policy(policy_id.to_string());
         let evaluation_environment = Arc::new(build_evaluation_environment());
         let validate_request =
             ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
@@ -1000,7 +1848,7 @@ mod tests {
                 Err(EvaluationError::PolicyNotFound(_))
             ));
             assert!(matches!(
-                evaluation_environment.validate(&policy_id, &validate_request),
+                evaluation_environment.validate(&policy_id, &validate_request, false),
                 Err(EvaluationError::PolicyNotFound(_))
             ));
         } else {
@@ -1017,7 +1865,7 @@ mod tests {
             );
             assert!(
                 evaluation_environment
-                    .validate(&policy_id, &validate_request)
+                    .validate(&policy_id, &validate_request, false)
                     .is_ok()
             );
         }
@@ -1040,6 +1888,27 @@ mod tests {
             },
         ]
     )]
+    #[case::all_policies_are_evaluated_in_parallel(
+        "group_policy_parallel_with_unhappy_or_bracket_happy_and_unhappy_bracket",
+        false,
+        vec![
+            admission_response::StatusCause {
+                field: Some("spec.policies.unhappy_policy_1".to_string()),
+                message: Some("failing as expected".to_string()),
+                ..Default::default()
+            },
+            admission_response::StatusCause {
+                field: Some("spec.policies.unhappy_policy_2".to_string()),
+                message: Some("failing as expected".to_string()),
+                ..Default::default()
+            },
+        ]
+    )]
+    #[case::members_in_monitor_mode_accept(
+        "group_policy_with_happy_and_monitored_unhappy",
+        true,
+        Vec::new(), // no expected causes, since the request is accepted
+    )]
     #[case::not_all_policies_are_evaluated(
         "group_policy_with_unhappy_or_happy_or_unhappy",
         true,
@@ -1068,8 +1937,9 @@ mod tests {
         );
 
         let response = evaluation_environment
-            .validate(&policy_id, &validate_request)
-            .expect("should not have errored");
+            .validate(&policy_id, &validate_request, false)
+            .expect("should not have errored")
+            .response;
         assert_eq!(response.allowed, admission_accepted);
         assert_eq!(response.warnings, None);
 
@@ -1092,6 +1962,149 @@ mod tests {
         }
     }
 
+    #[rstest]
+    #[case::member_and_request("group_policy_cel_happy_and_update", true)]
+    #[case::request_not_matching("group_policy_cel_happy_and_create", false)]
+    #[case::member_rejects("group_policy_cel_unhappy", false)]
+    fn validate_policy_group_with_cel_expression(#[case] policy_id: &str, #[case] allowed: bool) {
+        let policy_id = PolicyID::Policy(policy_id.to_string());
+        let evaluation_environment = build_evaluation_environment();
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluation_environment
+            .validate(&policy_id, &validate_request, false)
+            .expect("should not have errored")
+            .response;
+
+        assert_eq!(response.allowed, allowed);
+        if !allowed {
+            assert_eq!(
+                response.status.unwrap().message,
+                Some("something went wrong".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn validate_policy_group_with_trace() {
+        let policy_id =
+            PolicyID::Policy("group_policy_with_unhappy_or_happy_or_unhappy".to_string());
+        let evaluation_environment = build_evaluation_environment();
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluation_environment
+            .validate(&policy_id, &validate_request, true)
+            .expect("should not have errored")
+            .response;
+
+        assert!(response.allowed);
+        let audit_annotations = response
+            .audit_annotations
+            .expect("should have audit annotations");
+        // The expression short-circuits once `happy_policy_1` accepts the request
+        assert_eq!(audit_annotations.len(), 2);
+        let unhappy_policy_1: serde_json::Value =
+            serde_json::from_str(&audit_annotations["unhappy_policy_1"]).unwrap();
+        assert_eq!(unhappy_policy_1["allowed"], false);
+        assert_eq!(unhappy_policy_1["message"], "failing as expected");
+        let happy_policy_1: serde_json::Value =
+            serde_json::from_str(&audit_annotations["happy_policy_1"]).unwrap();
+        assert_eq!(happy_policy_1["allowed"], true);
+        assert!(happy_policy_1["durationMs"].is_u64());
+    }
+
+    #[rstest]
+    #[case::happy_member_is_cheaper(Duration::from_millis(1), Duration::from_secs(1), &["happy_policy_1"])]
+    #[case::unhappy_member_is_cheaper(
+        Duration::from_secs(1),
+        Duration::from_millis(1),
+        &["happy_policy_1", "unhappy_policy_1"]
+    )]
+    fn validate_policy_group_with_cel_expression_evaluates_the_cheapest_members_first(
+        #[case] happy_cost: Duration,
+        #[case] unhappy_cost: Duration,
+        #[case] expected_evaluated: &[&str],
+    ) {
+        let engine = wasmtime::Engine::default();
+        let (callback_handler_tx, _) = mpsc::channel(10);
+        let precompiled_policies: PrecompiledPolicies = HashMap::from([
+            (
+                "file:///tmp/happy_policy_1.wasm".to_string(),
+                Ok(build_precompiled_policy(
+                    &engine,
+                    include_bytes!("../../tests/data/gatekeeper_always_happy_policy.wasm"),
+                )),
+            ),
+            (
+                "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                Ok(build_precompiled_policy(
+                    &engine,
+                    include_bytes!("../../tests/data/gatekeeper_always_unhappy_policy.wasm"),
+                )),
+            ),
+        ]);
+        let member = |name: &str| PolicyGroupMember {
+            module: format!("file:///tmp/{name}.wasm"),
+            settings: None,
+            context_aware_resources: BTreeSet::new(),
+            timeout_eval_seconds: None,
+            policy_mode: PolicyMode::Protect,
+            on_timeout: OnTimeout::Error,
+        };
+        let policies = HashMap::from([(
+            "group".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                policies: ["happy_policy_1", "unhappy_policy_1"]
+                    .into_iter()
+                    .map(|name| (name.to_string(), member(name)))
+                    .collect(),
+                expression: "happy_policy_1 || unhappy_policy_1".to_string(),
+                expression_language: ExpressionLanguage::Cel,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: true,
+                message: "something went wrong".to_string(),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
+            },
+        )]);
+        let evaluation_environment =
+            EvaluationEnvironmentBuilder::new(&engine, &precompiled_policies, callback_handler_tx)
+                .build_evaluation_environment(&policies)
+                .unwrap();
+        for (name, cost) in [
+            ("happy_policy_1", happy_cost),
+            ("unhappy_policy_1", unhappy_cost),
+        ] {
+            evaluation_environment.policy_id_to_member_cost[&PolicyID::PolicyGroupPolicy {
+                group: "group".to_string(),
+                name: name.to_string(),
+            }]
+                .record(cost);
+        }
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = Arc::new(evaluation_environment)
+            .validate(
+                &PolicyID::Policy("group".to_string()),
+                &validate_request,
+                false,
+            )
+            .unwrap()
+            .response;
+
+        assert!(response.allowed);
+        let mut evaluated: Vec<String> = response.audit_annotations.unwrap().into_keys().collect();
+        evaluated.sort();
+        assert_eq!(evaluated, expected_evaluated);
+    }
+
     /// Given two identical wasm modules, only one instance of PolicyEvaluator is going to be
     /// created
     // Given two identical wasm modules in two different policies with different
@@ -1104,25 +2117,335 @@ mod tests {
             evaluation_environment
                 .module_digest_to_policy_evaluator_pre
                 .len(),
-            2 // 2 unique modules
+            3 // 2 unique modules, one of them is also used with a timeout
+        );
+    }
+
+    #[test]
+    fn list_policies() {
+        let mut evaluation_environment = build_evaluation_environment();
+        evaluation_environment.policy_initialization_errors.insert(
+            PolicyID::Policy("broken_policy".to_string()),
+            (FailureKind::Initialization, "boom".to_string()),
+        );
+
+        let policies = evaluation_environment.list_policies();
+        let policy_ids: Vec<&str> = policies.iter().map(|policy| policy.id.as_str()).collect();
+        let mut sorted_policy_ids = policy_ids.clone();
+        sorted_policy_ids.sort();
+        assert_eq!(policy_ids, sorted_policy_ids);
+
+        let broken_policy = policies
+            .iter()
+            .find(|policy| policy.id == "broken_policy")
+            .expect("policies with initialization errors should be listed");
+        assert_eq!(broken_policy.initialization_error, Some("boom".to_string()));
+        assert_eq!(broken_policy.policy_mode, None);
+
+        let policy = evaluation_environment
+            .get_policy_info(&PolicyID::Policy("policy_with_timeout".to_string()))
+            .unwrap();
+        assert_eq!(
+            policy.module,
+            Some("file:///tmp/happy_policy_1.wasm".to_string())
+        );
+        assert!(policy.module_digest.is_some());
+        assert_eq!(policy.policy_mode, Some("protect".to_string()));
+        assert_eq!(policy.allowed_to_mutate, Some(false));
+        assert_eq!(policy.timeout_eval_seconds, Some(5));
+        assert!(policy.group.is_none());
+
+        let group = evaluation_environment
+            .get_policy_info(&PolicyID::Policy(
+                "group_policy_with_unhappy_or_happy_or_unhappy".to_string(),
+            ))
+            .unwrap()
+            .group
+            .expect("should be a policy group");
+        assert_eq!(
+            group.expression,
+            "unhappy_policy_1() || happy_policy_1() || unhappy_policy_2()"
+        );
+        assert_eq!(group.members.len(), 3);
+
+        assert!(matches!(
+            evaluation_environment.get_policy_info(&PolicyID::Policy("not_defined".to_string())),
+            Err(EvaluationError::PolicyNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn validate_all_settings() {
+        let mut evaluation_environment = build_evaluation_environment();
+        evaluation_environment.policy_initialization_errors.insert(
+            PolicyID::Policy("broken_policy".to_string()),
+            (FailureKind::Initialization, "boom".to_string()),
+        );
+
+        let validation_errors = evaluation_environment.validate_all_settings();
+
+        assert_eq!(
+            validation_errors.get("broken_policy"),
+            Some(&Some("boom".to_string()))
+        );
+        assert_eq!(
+            validation_errors.get("group_policy_valid_expression_just_rhai"),
+            Some(&None)
+        );
+        assert!(matches!(
+            validation_errors.get("group_policy_not_valid_expression_because_of_typos"),
+            Some(Some(_))
+        ));
+        assert!(
+            validation_errors.contains_key(
+                &PolicyID::PolicyGroupPolicy {
+                    group: "group_policy_valid_expression_with_single_member".to_string(),
+                    name: "happy_policy_1".to_string(),
+                }
+                .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn reuse_policy_evaluators_pre_of_unchanged_modules() {
+        let engine = wasmtime::Engine::default();
+        let (callback_handler_tx, _) = mpsc::channel(10);
+        let precompiled_policy = build_precompiled_policy(
+            &engine,
+            include_bytes!("../../tests/data/gatekeeper_always_happy_policy.wasm"),
         );
+        let policy_url = "file:///tmp/happy_policy_1.wasm".to_string();
+        let precompiled_policies: PrecompiledPolicies =
+            HashMap::from([(policy_url.clone(), Ok(precompiled_policy.clone()))]);
+        let policies = HashMap::from([(
+            "happy_policy_1".to_string(),
+            PolicyOrPolicyGroup::Policy {
+                module: policy_url,
+                policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                settings: None,
+                context_aware_resources: BTreeSet::new(),
+                message: None,
+                timeout_eval_seconds: None,
+                failure_policy: None,
+                max_concurrency: None,
+                cache_ttl_seconds: None,
+                match_conditions: None,
+            },
+        )]);
+
+        let previous_evaluation_environment = EvaluationEnvironmentBuilder::new(
+            &engine,
+            &precompiled_policies,
+            callback_handler_tx.clone(),
+        )
+        .build_evaluation_environment(&policies)
+        .unwrap();
+        let previous_policy_evaluators_pre =
+            previous_evaluation_environment.policy_evaluators_pre();
+
+        let evaluation_environment =
+            EvaluationEnvironmentBuilder::new(&engine, &precompiled_policies, callback_handler_tx)
+                .with_policy_evaluators_pre(previous_policy_evaluators_pre.clone())
+                .build_evaluation_environment(&policies)
+                .unwrap();
+
+        let policy_evaluator_pre_key = (precompiled_policy.digest, None);
+        assert!(Arc::ptr_eq(
+            previous_policy_evaluators_pre
+                .get(&policy_evaluator_pre_key)
+                .unwrap(),
+            evaluation_environment
+                .module_digest_to_policy_evaluator_pre
+                .get(&policy_evaluator_pre_key)
+                .unwrap(),
+        ));
+    }
+
+    #[rstest]
+    #[case::policy_timeout_changed(Some(5), None, Some(10), None, Some(10))]
+    #[case::global_limit_changed(None, Some(5), None, Some(10), Some(10))]
+    fn do_not_reuse_policy_evaluators_pre_built_with_another_epoch_deadline(
+        #[case] previous_timeout_eval_seconds: Option<u64>,
+        #[case] previous_global_limit_seconds: Option<u64>,
+        #[case] timeout_eval_seconds: Option<u64>,
+        #[case] global_limit_seconds: Option<u64>,
+        #[case] expected_epoch_deadline: Option<u64>,
+    ) {
+        let engine = wasmtime::Engine::default();
+        let (callback_handler_tx, _) = mpsc::channel(10);
+        let precompiled_policy = build_precompiled_policy(
+            &engine,
+            include_bytes!("../../tests/data/gatekeeper_always_happy_policy.wasm"),
+        );
+        let policy_url = "file:///tmp/happy_policy_1.wasm".to_string();
+        let precompiled_policies: PrecompiledPolicies =
+            HashMap::from([(policy_url.clone(), Ok(precompiled_policy.clone()))]);
+        let policies = |timeout_eval_seconds| {
+            HashMap::from([(
+                "happy_policy_1".to_string(),
+                PolicyOrPolicyGroup::Policy {
+                    module: policy_url.clone(),
+                    policy_mode: PolicyMode::Protect,
+                    allowed_to_mutate: None,
+                    settings: None,
+                    context_aware_resources: BTreeSet::new(),
+                    message: None,
+                    timeout_eval_seconds,
+                    failure_policy: None,
+                    max_concurrency: None,
+                    cache_ttl_seconds: None,
+                    match_conditions: None,
+                },
+            )])
+        };
+        let builder = |global_limit_seconds: Option<u64>| {
+            let builder = EvaluationEnvironmentBuilder::new(
+                &engine,
+                &precompiled_policies,
+                callback_handler_tx.clone(),
+            );
+            match global_limit_seconds {
+                Some(limit) => builder.with_global_policy_evaluation_limit_seconds(limit),
+                None => builder,
+            }
+        };
+
+        let previous_policy_evaluators_pre = builder(previous_global_limit_seconds)
+            .build_evaluation_environment(&policies(previous_timeout_eval_seconds))
+            .unwrap()
+            .policy_evaluators_pre();
+
+        let evaluation_environment = builder(global_limit_seconds)
+            .with_policy_evaluators_pre(previous_policy_evaluators_pre.clone())
+            .build_evaluation_environment(&policies(timeout_eval_seconds))
+            .unwrap();
+
+        let policy_evaluators_pre = evaluation_environment.policy_evaluators_pre();
+        assert_eq!(
+            policy_evaluators_pre.keys().collect::<Vec<_>>(),
+            vec![&(precompiled_policy.digest, expected_epoch_deadline)]
+        );
+        let previous_policy_evaluator_pre = previous_policy_evaluators_pre.values().next().unwrap();
+        let policy_evaluator_pre = policy_evaluators_pre.values().next().unwrap();
+        assert!(!Arc::ptr_eq(
+            previous_policy_evaluator_pre,
+            policy_evaluator_pre
+        ));
+    }
+
+    #[rstest]
+    #[case::opted_in(Some(NonZeroU64::new(60).unwrap()))]
+    #[case::not_opted_in(None)]
+    fn cache_the_responses_of_the_policies(#[case] cache_ttl_seconds: Option<NonZeroU64>) {
+        let engine = wasmtime::Engine::default();
+        let (callback_handler_tx, _) = mpsc::channel(10);
+        let precompiled_policy = build_precompiled_policy(
+            &engine,
+            include_bytes!("../../tests/data/gatekeeper_always_happy_policy.wasm"),
+        );
+        let policy_url = "file:///tmp/happy_policy_1.wasm".to_string();
+        let precompiled_policies: PrecompiledPolicies =
+            HashMap::from([(policy_url.clone(), Ok(precompiled_policy))]);
+        let policies = HashMap::from([(
+            "happy_policy_1".to_string(),
+            PolicyOrPolicyGroup::Policy {
+                module: policy_url,
+                policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                settings: None,
+                context_aware_resources: BTreeSet::new(),
+                message: None,
+                timeout_eval_seconds: None,
+                failure_policy: None,
+                max_concurrency: None,
+                cache_ttl_seconds,
+                match_conditions: None,
+            },
+        )]);
+        let evaluation_cache = Arc::new(EvaluationCache::new(NonZeroUsize::new(10).unwrap()));
+
+        let evaluation_environment = Arc::new(
+            EvaluationEnvironmentBuilder::new(&engine, &precompiled_policies, callback_handler_tx)
+                .with_evaluation_cache(evaluation_cache.clone())
+                .build_evaluation_environment(&policies)
+                .unwrap(),
+        );
+
+        let policy_id = PolicyID::Policy("happy_policy_1".to_string());
+        let request = build_admission_review_request().request;
+        let validate_request = ValidateRequest::AdmissionRequest(Box::new(request.clone()));
+        let response = evaluation_environment
+            .validate(&policy_id, &validate_request, false)
+            .unwrap()
+            .response;
+        assert!(response.allowed);
+
+        let cached_response = evaluation_environment
+            .policy_id_to_cache_digest
+            .get(&policy_id)
+            .and_then(|(cache_digest, _)| {
+                evaluation_cache::cache_key(&policy_id, cache_digest, &request)
+            })
+            .and_then(|key| evaluation_cache.get(&key, "other-uid"));
+        assert_eq!(cached_response.is_some(), cache_ttl_seconds.is_some());
+        if let Some(cached_response) = cached_response {
+            assert_eq!(cached_response.uid, "other-uid");
+            assert!(cached_response.allowed);
+        }
     }
 
     #[test]
     fn validate_policy_with_initialization_error() {
         let mut evaluation_environment = build_evaluation_environment();
         let policy_id = PolicyID::Policy("policy_3".to_string());
-        evaluation_environment
-            .policy_initialization_errors
-            .insert(policy_id.clone(), "error".to_string());
+        evaluation_environment.policy_initialization_errors.insert(
+            policy_id.clone(),
+            (FailureKind::SettingsInvalid, "error".to_string()),
+        );
         let evaluation_environment = Arc::new(evaluation_environment);
 
         let validate_request =
             ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
-        assert!(matches!(
-            evaluation_environment.validate(&policy_id, &validate_request).unwrap_err(),
-            EvaluationError::PolicyInitialization(error) if error == "error"
-        ));
+        let validation_response = evaluation_environment
+            .validate(&policy_id, &validate_request, false)
+            .unwrap();
+        assert_eq!(
+            validation_response.failure,
+            Some(FailureKind::SettingsInvalid)
+        );
+        assert!(!validation_response.response.allowed);
+        assert_eq!(
+            validation_response.response.status.unwrap().message,
+            Some("error".to_string())
+        );
+    }
+
+    #[test]
+    fn policies_max_concurrency() {
+        let evaluation_environment = build_evaluation_environment();
+
+        assert_eq!(
+            evaluation_environment.policies_max_concurrency(),
+            HashMap::from([(
+                "policy_with_timeout".to_string(),
+                NonZeroUsize::new(2).unwrap()
+            )])
+        );
+    }
+
+    #[rstest]
+    #[case::with_failure_policy("policy_with_timeout", Some(FailurePolicy::Ignore))]
+    #[case::without_failure_policy("happy_policy_1", None)]
+    fn get_policy_failure_policy(#[case] policy_id: &str, #[case] expected: Option<FailurePolicy>) {
+        let evaluation_environment = build_evaluation_environment();
+        let policy_id = PolicyID::Policy(policy_id.to_string());
+
+        assert_eq!(
+            evaluation_environment.get_policy_failure_policy(&policy_id),
+            expected
+        );
     }
 
     #[rstest]
@@ -1143,11 +2466,19 @@ mod tests {
         "group_policy_not_valid_expression_because_doing_operations_with_booleans_is_wrong",
         false
     )]
-    // This doesn't test doesn't pass: the int is automatically converted to boolean
-    // #[case::not_valid_expression_because_does_not_return_boolean(
-    //     "group_policy_not_valid_expression_because_does_not_return_boolean",
-    //     false
-    // )]
+    #[case::not_valid_expression_because_does_not_return_boolean(
+        "group_policy_not_valid_expression_because_of_does_not_return_boolean",
+        false
+    )]
+    #[case::valid_cel_expression("group_policy_cel_happy_and_update", true)]
+    #[case::not_valid_cel_expression_because_of_unknown_policy(
+        "group_policy_cel_not_valid_expression_because_of_unknown_policy",
+        false
+    )]
+    #[case::not_valid_cel_expression_because_of_typos(
+        "group_policy_cel_not_valid_expression_because_of_typos",
+        false
+    )]
     fn validate_policy_settings_of_policy_group(
         #[case] policy_id: &str,
         #[case] expression_is_valid: bool,
@@ -1158,7 +2489,7 @@ mod tests {
         // However we ignore these errors because we are only interested in the validation of the
         // expression of the group policy
 
-        let mut evaluation_environment = build_evaluation_environment();
+        let evaluation_environment = build_evaluation_environment();
         let validation_result = evaluation_environment.validate_settings(&policy_id);
 
         assert_eq!(expression_is_valid, validation_result.is_ok());
@@ -0,0 +1,182 @@
+use std::collections::BTreeSet;
+
+use lazy_static::lazy_static;
+use policy_evaluator::admission_request::AdmissionRequest;
+use rhai::{AST, Dynamic, Engine, Scope};
+
+use crate::config::MatchConditions;
+
+lazy_static! {
+    static ref ENGINE: Engine = {
+        let mut engine = Engine::new();
+        // The expressions are evaluated for each request, prevent them from
+        // taking too long
+        engine.set_max_operations(10_000);
+        engine
+    };
+}
+
+/// The `MatchConditions` of a policy, with the expression compiled ahead of time.
+#[derive(Clone, Debug)]
+pub(crate) struct CompiledMatchConditions {
+    conditions: MatchConditions,
+    expression: Option<AST>,
+}
+
+impl CompiledMatchConditions {
+    /// Compile the expression of the given conditions. An error is returned when the
+    /// expression is not valid
+    pub(crate) fn new(conditions: &MatchConditions) -> Result<Self, String> {
+        let expression = conditions
+            .expression
+            .as_ref()
+            .map(|expression| {
+                ENGINE
+                    .compile_expression(expression)
+                    .map_err(|e| format!("invalid match conditions expression '{expression}': {e}"))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            conditions: conditions.clone(),
+            expression,
+        })
+    }
+
+    /// Returns `true` when the request satisfies all the conditions
+    pub(crate) fn matches(&self, request: &AdmissionRequest) -> Result<bool, String> {
+        let conditions = &self.conditions;
+
+        let namespace = request.namespace.as_deref().unwrap_or_default();
+        let username = request.user_info.username.as_deref().unwrap_or_default();
+        let groups = request.user_info.groups.as_deref().unwrap_or_default();
+
+        let satisfied = allows(&conditions.operations, &request.operation)
+            && allows(&conditions.kinds, &request.kind.kind)
+            && allows(&conditions.namespaces, namespace)
+            && allows(&conditions.users, username)
+            && (conditions.groups.is_empty()
+                || groups.iter().any(|group| conditions.groups.contains(group)));
+        if !satisfied {
+            return Ok(false);
+        }
+
+        let Some(expression) = &self.expression else {
+            return Ok(true);
+        };
+
+        let request = rhai::serde::to_dynamic(request)
+            .map_err(|e| format!("cannot convert the request: {e}"))?;
+        let mut scope = Scope::new();
+        scope.push_constant_dynamic("request", request);
+
+        ENGINE
+            .eval_ast_with_scope::<Dynamic>(&mut scope, expression)
+            .map_err(|e| format!("cannot evaluate the match conditions expression: {e}"))?
+            .as_bool()
+            .map_err(|type_name| {
+                format!("the match conditions expression returned a {type_name} instead of a bool")
+            })
+    }
+}
+
+/// Returns `true` when `value` is one of the `allowed` ones, or when no value is given
+fn allows(allowed: &BTreeSet<String>, value: &str) -> bool {
+    allowed.is_empty() || allowed.contains(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::build_admission_review_request;
+    use rstest::*;
+
+    #[rstest]
+    #[case::no_conditions(MatchConditions::default(), true)]
+    #[case::operation(
+        MatchConditions {
+            operations: ["CREATE".to_owned(), "UPDATE".to_owned()].into(),
+            ..Default::default()
+        },
+        true
+    )]
+    #[case::other_operation(
+        MatchConditions {
+            operations: ["DELETE".to_owned()].into(),
+            ..Default::default()
+        },
+        false
+    )]
+    #[case::other_kind(
+        MatchConditions {
+            kinds: ["Pod".to_owned()].into(),
+            ..Default::default()
+        },
+        false
+    )]
+    #[case::namespace_and_user(
+        MatchConditions {
+            namespaces: ["my-namespace".to_owned()].into(),
+            users: ["admin".to_owned()].into(),
+            ..Default::default()
+        },
+        true
+    )]
+    #[case::one_of_the_groups(
+        MatchConditions {
+            groups: ["my-admin-group".to_owned(), "other-group".to_owned()].into(),
+            ..Default::default()
+        },
+        true
+    )]
+    #[case::expression(
+        MatchConditions {
+            expression: Some(r#"request.subResource == "scale" && !request.dryRun"#.to_owned()),
+            ..Default::default()
+        },
+        true
+    )]
+    #[case::expression_not_satisfied(
+        MatchConditions {
+            kinds: ["Scale".to_owned()].into(),
+            expression: Some(r#"request.object.kind == "Deployment""#.to_owned()),
+            ..Default::default()
+        },
+        false
+    )]
+    fn matches(#[case] conditions: MatchConditions, #[case] expected: bool) {
+        let request = build_admission_review_request().request;
+        let match_conditions = CompiledMatchConditions::new(&conditions).unwrap();
+
+        assert_eq!(match_conditions.matches(&request).unwrap(), expected);
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        let conditions = MatchConditions {
+            expression: Some("request.operation ==".to_owned()),
+            ..Default::default()
+        };
+
+        assert!(CompiledMatchConditions::new(&conditions).is_err());
+    }
+
+    #[test]
+    fn expressions_must_return_a_bool() {
+        let request = build_admission_review_request().request;
+        let conditions = MatchConditions {
+            expression: Some("request.operation".to_owned()),
+            ..Default::default()
+        };
+
+        let error = CompiledMatchConditions::new(&conditions)
+            .unwrap()
+            .matches(&request)
+            .unwrap_err();
+
+        assert!(
+            error.contains("instead of a bool"),
+            "unexpected error: {error}"
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::config::PolicyOrPolicyGroupSettings;
+use crate::config::{OnTimeout, PolicyOrPolicyGroupSettings};
 use policy_evaluator::admission_response_handler::policy_mode::PolicyMode;
 
 /// Holds the evaluation settings of loaded Policy. These settings are taken straight from the
@@ -16,4 +16,7 @@ pub(crate) struct PolicyEvaluationSettings {
     pub(crate) custom_rejection_message: Option<String>,
     /// Timeout for the evaluation of the policy in seconds
     pub(crate) timeout_eval_seconds: Option<u64>,
+    /// How a policy group treats the policy when its evaluation times out, used only by
+    /// the members of the groups
+    pub(crate) on_timeout: OnTimeout,
 }
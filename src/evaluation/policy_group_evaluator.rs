@@ -0,0 +1,957 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use cel_interpreter::{Context, Program, Value};
+use policy_evaluator::{
+    admission_response::{
+        AdmissionResponse, AdmissionResponseStatus, PatchType, StatusCause, StatusDetails,
+    },
+    admission_response_handler::policy_mode::PolicyMode,
+    evaluation_context::EvaluationContext,
+    policy_evaluator::{PolicyEvaluatorPre, PolicySettings, ValidateRequest},
+};
+use rhai::{ASTNode, Engine, Expr, Stmt};
+use serde::Serialize;
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::{ExpressionLanguage, MembersEvaluation, OnTimeout},
+    evaluation::{
+        policy_timeout,
+        validation_response::{FailureKind, ValidationResponse},
+    },
+    metrics,
+};
+
+mod mutation_chain;
+use mutation_chain::MutationChain;
+
+/// The name of the CEL variable holding the request being evaluated
+const CEL_REQUEST_VARIABLE: &str = "request";
+
+/// The name of the Rhai function, and of the CEL variable, telling which members timed out
+const TIMED_OUT: &str = "timed_out";
+
+/// How long a member evaluated in parallel can take after its execution limit, before the
+/// group stops waiting for its response
+const MEMBER_WAIT_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// The weight of the last evaluation inside of the moving average of the duration of a member
+const MEMBER_COST_SMOOTHING: u32 = 8;
+
+/// A member of a policy group, together with everything needed to evaluate it
+pub(crate) struct PolicyGroupMemberEvaluator {
+    pub(crate) policy_evaluator_pre: Arc<PolicyEvaluatorPre>,
+    pub(crate) eval_ctx: EvaluationContext,
+    pub(crate) settings: PolicySettings,
+    pub(crate) policy_mode: PolicyMode,
+    pub(crate) on_timeout: OnTimeout,
+    pub(crate) cost: Arc<MemberCost>,
+}
+
+/// How long the evaluation of a member takes: an exponential moving average of the duration
+/// of its evaluations. It is shared by all the evaluations of the group, the cheapest members
+/// are evaluated first.
+#[derive(Default)]
+pub(crate) struct MemberCost {
+    average: Mutex<Option<Duration>>,
+}
+
+impl MemberCost {
+    pub(crate) fn record(&self, duration: Duration) {
+        let mut average = self
+            .average
+            .lock()
+            .expect("cannot acquire lock on the member cost");
+        *average = Some(match *average {
+            Some(average) if duration >= average => {
+                average + (duration - average) / MEMBER_COST_SMOOTHING
+            }
+            Some(average) => average - (average - duration) / MEMBER_COST_SMOOTHING,
+            None => duration,
+        });
+    }
+
+    /// `None` when the member has never been evaluated
+    fn estimate(&self) -> Option<Duration> {
+        *self
+            .average
+            .lock()
+            .expect("cannot acquire lock on the member cost")
+    }
+}
+
+/// The outcome of the evaluation of a member
+struct MemberEvaluation {
+    response: AdmissionResponse,
+    duration: Duration,
+    timed_out: bool,
+    /// Set when the member could not be evaluated, this fails the evaluation of the group
+    error: Option<String>,
+}
+
+/// The result of a member, as reported inside of the audit annotations of the group response
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MemberTrace {
+    allowed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<u16>,
+    duration_ms: u64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+}
+
+/// The responses of the members evaluated so far, indexed by the name of the member.
+/// Each member is evaluated at most once, even when the expression references it
+/// multiple times.
+#[derive(Default)]
+struct MemberResponses {
+    state: Mutex<MemberResponsesState>,
+    /// Notified each time the response of a member is added
+    added: Condvar,
+}
+
+#[derive(Default)]
+struct MemberResponsesState {
+    evaluations: BTreeMap<String, MemberEvaluation>,
+    /// The members being evaluated
+    running: HashSet<String>,
+    /// Set once the group replied, the members that are not running yet are not evaluated
+    closed: bool,
+    /// The kind of the first failure reported to the group
+    failure: Option<FailureKind>,
+}
+
+impl MemberResponses {
+    /// Reserve the evaluation of the given member to the caller. Returns `false` when the
+    /// member is already running or evaluated, or when the group already replied.
+    fn claim(&self, name: &str) -> bool {
+        let mut state = self.lock();
+        if state.closed || state.running.contains(name) || state.evaluations.contains_key(name) {
+            return false;
+        }
+        state.running.insert(name.to_owned())
+    }
+
+    /// Wait up to `limit` for the response of the given member, then returns whether it
+    /// accepted the request. There's no limit when the member has no execution limit.
+    fn wait_allowed(&self, name: &str, limit: Option<Duration>) -> Result<bool, String> {
+        let deadline = limit.map(|limit| Instant::now() + limit);
+        let mut state = self.lock();
+        loop {
+            if let Some(evaluation) = state.evaluations.get(name) {
+                return match evaluation.error.clone() {
+                    Some(error) => {
+                        state.failure.get_or_insert(FailureKind::WebAssembly);
+                        Err(error)
+                    }
+                    None => Ok(evaluation.response.allowed),
+                };
+            }
+            state = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        state.failure.get_or_insert(FailureKind::Timeout);
+                        return Err(format!(
+                            "timed out waiting for the evaluation of the policy {name}"
+                        ));
+                    }
+                    self.added
+                        .wait_timeout(state, remaining)
+                        .expect("cannot acquire lock on the member responses")
+                        .0
+                }
+                None => self
+                    .added
+                    .wait(state)
+                    .expect("cannot acquire lock on the member responses"),
+            };
+        }
+    }
+
+    /// The members that are not running yet are not evaluated anymore
+    fn close(&self) {
+        self.lock().closed = true;
+    }
+
+    /// Record the kind of a failure that fails the evaluation of the group. Only the first
+    /// failure is kept.
+    fn fail(&self, kind: FailureKind) {
+        self.lock().failure.get_or_insert(kind);
+    }
+
+    /// Returns the kind of the first failure reported to the group
+    fn failure(&self) -> Option<FailureKind> {
+        self.lock().failure
+    }
+
+    /// Returns whether the evaluation of the given member timed out
+    fn timed_out(&self, name: &str) -> bool {
+        self.lock()
+            .evaluations
+            .get(name)
+            .is_some_and(|evaluation| evaluation.timed_out)
+    }
+
+    /// Returns the members whose evaluation timed out and whose timeout must fail the
+    /// evaluation of the whole group. The members in monitor mode never change the outcome
+    /// of the group, their timeouts are only reported
+    fn failing_timeouts(
+        &self,
+        members: &BTreeMap<String, Arc<PolicyGroupMemberEvaluator>>,
+    ) -> Vec<String> {
+        self.lock()
+            .evaluations
+            .iter()
+            .filter(|(name, evaluation)| {
+                evaluation.timed_out
+                    && members.get(*name).is_some_and(|member| {
+                        member.on_timeout == OnTimeout::Error
+                            && !matches!(member.policy_mode, PolicyMode::Monitor)
+                    })
+            })
+            .map(|(name, _)| name.to_owned())
+            .collect()
+    }
+
+    fn insert(&self, name: &str, evaluation: MemberEvaluation) {
+        let mut state = self.lock();
+        state.running.remove(name);
+        state.evaluations.insert(name.to_owned(), evaluation);
+        self.added.notify_all();
+    }
+
+    /// Returns the rejections of the members evaluated so far
+    fn rejections(&self) -> Vec<StatusCause> {
+        self.lock()
+            .evaluations
+            .iter()
+            .filter(|(_, evaluation)| !evaluation.response.allowed)
+            .map(|(name, evaluation)| StatusCause {
+                field: Some(format!("spec.policies.{name}")),
+                message: evaluation
+                    .response
+                    .status
+                    .as_ref()
+                    .and_then(|status| status.message.clone()),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Returns the results of the members evaluated so far, serialized as JSON and
+    /// indexed by the name of the member. The members not evaluated are not included.
+    fn trace(&self) -> HashMap<String, String> {
+        self.lock()
+            .evaluations
+            .iter()
+            .map(|(name, evaluation)| {
+                let status = evaluation.response.status.as_ref();
+                let trace = MemberTrace {
+                    allowed: evaluation.response.allowed,
+                    message: status.and_then(|status| status.message.clone()),
+                    code: status.and_then(|status| status.code),
+                    duration_ms: u64::try_from(evaluation.duration.as_millis()).unwrap_or(u64::MAX),
+                    timed_out: evaluation.timed_out,
+                };
+                (
+                    name.to_owned(),
+                    serde_json::to_string(&trace).expect("cannot serialize the member trace"),
+                )
+            })
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemberResponsesState> {
+        self.state
+            .lock()
+            .expect("cannot acquire lock on the member responses")
+    }
+}
+
+/// Evaluates a policy group: the members are evaluated on demand, their results are
+/// then combined by the expression of the group.
+///
+/// The expression can be written either in Rhai or in CEL:
+/// - Rhai: each member is a function returning `true` when the member accepts the request
+/// - CEL: each member is a boolean variable, the request is available as `request`
+///
+/// When the members are evaluated in parallel, all the members referenced by the expression
+/// are queued on the rayon thread pool, the cheapest ones first, see `members_by_cost`.
+/// The expression is evaluated right away and waits only for the results it needs; a member
+/// still queued when the expression needs it is evaluated on the current thread. Once the
+/// outcome is known, the group replies without waiting for the remaining members: the ones
+/// still queued are not evaluated, the running ones cannot be interrupted and complete in
+/// the background, using CPU time for a response that is discarded.
+///
+/// The mutators are the only members allowed to mutate the request. They are evaluated
+/// one after the other before the expression, each one receiving the object mutated by the
+/// previous ones. The other members validate the mutated object, and the group returns a
+/// single patch combining the ones of the mutators.
+///
+/// When tracing is enabled, the results of the members are reported inside of the audit
+/// annotations of the response, see `MemberTrace`.
+///
+/// This replaces the `PolicyGroupEvaluator` of `policy-evaluator`, which builds its own Rhai
+/// engine: it cannot evaluate another expression language and it does not expose the
+/// responses of the members. Only the combination of the members lives here, they are
+/// still evaluated by their `PolicyEvaluatorPre`, like the individual policies. This can
+/// go away once `policy-evaluator` supports CEL.
+pub(crate) struct PolicyGroupEvaluator {
+    policy_id: String,
+    message: String,
+    expression: String,
+    expression_language: ExpressionLanguage,
+    members_evaluation: MembersEvaluation,
+    mutators: Vec<String>,
+    trace: bool,
+    members: BTreeMap<String, Arc<PolicyGroupMemberEvaluator>>,
+}
+
+impl PolicyGroupEvaluator {
+    pub(crate) fn new(
+        policy_id: &str,
+        message: &str,
+        expression: &str,
+        expression_language: ExpressionLanguage,
+        members_evaluation: MembersEvaluation,
+    ) -> Self {
+        Self {
+            policy_id: policy_id.to_owned(),
+            message: message.to_owned(),
+            expression: expression.to_owned(),
+            expression_language,
+            members_evaluation,
+            mutators: Vec::new(),
+            trace: false,
+            members: BTreeMap::new(),
+        }
+    }
+
+    /// Set the members allowed to mutate the request, in the order they are evaluated
+    pub(crate) fn with_mutators(mut self, mutators: Vec<String>) -> Self {
+        self.mutators = mutators;
+        self
+    }
+
+    /// Report the results of the members inside of all the responses
+    pub(crate) fn with_trace(mut self, trace: bool) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    pub(crate) fn add_policy_member(&mut self, name: &str, member: PolicyGroupMemberEvaluator) {
+        self.members.insert(name.to_owned(), Arc::new(member));
+    }
+
+    /// Validate the request. The group rejects the request when its expression evaluates
+    /// to `false`, the rejections of the members are reported as causes of the response.
+    ///
+    /// The results of the members are reported when `trace` is set, even if the tracing
+    /// of the group is not enabled.
+    ///
+    /// The evaluation of the group fails when a member cannot be evaluated, when a member
+    /// times out and its `on_timeout` is `error`, or when the expression cannot be evaluated.
+    pub(crate) fn validate(&self, request: &ValidateRequest, trace: bool) -> ValidationResponse {
+        let uid = request.uid().to_owned();
+        let responses = Arc::new(MemberResponses::default());
+
+        let outcome = self
+            .mutate(request, &responses)
+            .and_then(|(request, patch)| {
+                let request = Arc::new(request);
+                if self.members_evaluation == MembersEvaluation::Parallel {
+                    self.start_members(&request, &responses);
+                }
+
+                let allowed = match self.expression_language {
+                    ExpressionLanguage::Rhai => self.evaluate_rhai(&request, &responses),
+                    ExpressionLanguage::Cel => self.evaluate_cel(&request, &responses),
+                }
+                .map_err(|e| format!("cannot evaluate the policy group expression: {e}"))?;
+
+                let failing_timeouts = responses.failing_timeouts(&self.members);
+                if !failing_timeouts.is_empty() {
+                    responses.fail(FailureKind::Timeout);
+                    return Err(format!(
+                        "the evaluation of the policies {failing_timeouts:?} timed out"
+                    ));
+                }
+
+                Ok((allowed, patch))
+            });
+        responses.close();
+
+        let mut validation_response = match outcome {
+            Ok((true, patch)) => ValidationResponse::verdict(AdmissionResponse {
+                uid,
+                allowed: true,
+                patch_type: patch.as_ref().map(|_| PatchType::JSONPatch),
+                patch,
+                ..Default::default()
+            }),
+            Ok((false, _)) => {
+                let causes = responses.rejections();
+
+                ValidationResponse::verdict(AdmissionResponse {
+                    uid,
+                    allowed: false,
+                    status: Some(AdmissionResponseStatus {
+                        message: Some(self.message.clone()),
+                        details: Some(StatusDetails {
+                            causes,
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+            }
+            Err(e) => ValidationResponse::failure(
+                uid,
+                responses.failure().unwrap_or(FailureKind::Internal),
+                format!("{}: {e}", self.policy_id),
+            ),
+        };
+
+        if trace || self.trace {
+            validation_response.response.audit_annotations = Some(responses.trace());
+        }
+
+        validation_response
+    }
+
+    /// Evaluate the mutators, one after the other. Returns the request mutated by them,
+    /// together with the base64 encoded JSONPatch combining their patches.
+    fn mutate(
+        &self,
+        request: &ValidateRequest,
+        responses: &MemberResponses,
+    ) -> Result<(ValidateRequest, Option<String>), String> {
+        if self.mutators.is_empty() {
+            return Ok((request.clone(), None));
+        }
+        let ValidateRequest::AdmissionRequest(admission_request) = request else {
+            return Err("mutating policy groups can evaluate only admission requests".to_owned());
+        };
+
+        let mut chain = MutationChain::new(admission_request)?;
+        for name in &self.mutators {
+            let member = self
+                .members
+                .get(name)
+                .ok_or_else(|| format!("unknown mutator {name}"))?;
+            let evaluation = evaluate_member(name, member, &chain.request()?, true);
+            if let Some(error) = &evaluation.error {
+                responses.fail(FailureKind::WebAssembly);
+                return Err(error.clone());
+            }
+            if let Some(patch) = &evaluation.response.patch {
+                chain.apply(name, patch)?;
+            }
+            responses.insert(name, evaluation);
+        }
+
+        Ok((chain.request()?, chain.patch()?))
+    }
+
+    /// Ensure the expression is valid and references only the members of the group
+    pub(crate) fn validate_settings(&self) -> Result<(), String> {
+        match self.expression_language {
+            ExpressionLanguage::Rhai => {
+                // Evaluate the expression assuming all the members accept the request, this
+                // catches the syntax errors, the unknown functions and the type errors
+                let mut engine = Engine::new();
+                for name in self.members.keys() {
+                    engine.register_fn(name.as_str(), || true);
+                }
+                engine.register_fn(TIMED_OUT, |_: &str| false);
+                engine
+                    .eval::<bool>(&self.expression)
+                    .map(|_| ())
+                    .map_err(|e| format!("invalid expression '{}': {e}", self.expression))
+            }
+            ExpressionLanguage::Cel => {
+                let program = Program::compile(&self.expression)
+                    .map_err(|e| format!("invalid expression '{}': {e}", self.expression))?;
+                let references = program.references();
+                let unknown_variables: Vec<&str> = references
+                    .variables()
+                    .into_iter()
+                    .filter(|variable| {
+                        *variable != CEL_REQUEST_VARIABLE
+                            && *variable != TIMED_OUT
+                            && !self.members.contains_key(*variable)
+                    })
+                    .collect();
+                if !unknown_variables.is_empty() {
+                    return Err(format!(
+                        "invalid expression '{}': unknown policies {unknown_variables:?}",
+                        self.expression
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Sort the given members by cost, see `sort_by_cost`
+    fn members_by_cost(&self, mut names: Vec<String>) -> Vec<String> {
+        sort_by_cost(&mut names, |name| {
+            self.members
+                .get(name)
+                .and_then(|member| member.cost.estimate())
+        });
+        names
+    }
+
+    /// Queue the evaluation of the members referenced by the expression on the rayon thread
+    /// pool, the cheapest first. Their responses are added to `responses` as soon as they are
+    /// available.
+    fn start_members(&self, request: &Arc<ValidateRequest>, responses: &Arc<MemberResponses>) {
+        let references = expression_references(&self.expression, self.expression_language);
+        for name in self.members_by_cost(references) {
+            if !self.members.contains_key(&name) || self.mutators.contains(&name) {
+                continue;
+            }
+            let member = self.members[&name].clone();
+            let request = request.clone();
+            let responses = responses.clone();
+            rayon::spawn(move || {
+                // The member has already been evaluated by the expression, or the group
+                // already replied
+                if !responses.claim(&name) {
+                    return;
+                }
+                let evaluation = evaluate_member(&name, &member, &request, false);
+                responses.insert(&name, evaluation);
+            });
+        }
+    }
+
+    /// Returns whether the given member accepts the request. The member is evaluated on the
+    /// current thread, unless its evaluation has already been started on the rayon thread
+    /// pool: in that case, this waits for its response up to its execution limit.
+    fn member_allowed(
+        name: &str,
+        member: &PolicyGroupMemberEvaluator,
+        request: &ValidateRequest,
+        responses: &MemberResponses,
+    ) -> Result<bool, String> {
+        if !responses.claim(name) {
+            let limit = member
+                .eval_ctx
+                .epoch_deadline
+                .map(|seconds| Duration::from_secs(seconds) + MEMBER_WAIT_GRACE_PERIOD);
+            return responses.wait_allowed(name, limit);
+        }
+
+        let evaluation = evaluate_member(name, member, request, false);
+        let allowed = match &evaluation.error {
+            Some(error) => {
+                responses.fail(FailureKind::WebAssembly);
+                Err(error.clone())
+            }
+            None => Ok(evaluation.response.allowed),
+        };
+        responses.insert(name, evaluation);
+        allowed
+    }
+
+    /// Evaluate the Rhai expression. The members are needed only when the expression
+    /// calls them, hence `||` and `&&` short-circuit their evaluation.
+    fn evaluate_rhai(
+        &self,
+        request: &Arc<ValidateRequest>,
+        responses: &Arc<MemberResponses>,
+    ) -> Result<bool, String> {
+        let mut engine = Engine::new();
+        for (name, member) in &self.members {
+            let member_name = name.clone();
+            let member = member.clone();
+            let request = request.clone();
+            let responses = responses.clone();
+            engine.register_fn(
+                name.as_str(),
+                move || -> Result<bool, Box<rhai::EvalAltResult>> {
+                    Ok(Self::member_allowed(
+                        &member_name,
+                        &member,
+                        &request,
+                        &responses,
+                    )?)
+                },
+            );
+        }
+
+        // `timed_out("member")` evaluates the member, when needed, then tells whether its
+        // evaluation timed out
+        let members = self.members.clone();
+        let request = request.clone();
+        let responses = responses.clone();
+        engine.register_fn(
+            TIMED_OUT,
+            move |name: &str| -> Result<bool, Box<rhai::EvalAltResult>> {
+                let member = members
+                    .get(name)
+                    .ok_or_else(|| format!("unknown policy {name}"))?;
+                Self::member_allowed(name, member, &request, &responses)?;
+                Ok(responses.timed_out(name))
+            },
+        );
+
+        engine
+            .eval::<bool>(&self.expression)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Evaluate the CEL expression. The members referenced by the expression are evaluated
+    /// one after the other, the cheapest first. The expression is executed after each of
+    /// them: the remaining members are not evaluated once it no longer depends on them,
+    /// unless the expression checks the timeouts of the members.
+    fn evaluate_cel(
+        &self,
+        request: &ValidateRequest,
+        responses: &MemberResponses,
+    ) -> Result<bool, String> {
+        let program = Program::compile(&self.expression).map_err(|e| e.to_string())?;
+
+        let mut context = Context::default();
+        match request {
+            ValidateRequest::AdmissionRequest(admission_request) => {
+                context.add_variable(CEL_REQUEST_VARIABLE, admission_request.as_ref())
+            }
+            ValidateRequest::Raw(raw_request) => {
+                context.add_variable(CEL_REQUEST_VARIABLE, raw_request)
+            }
+        }
+        .map_err(|e| format!("cannot convert the request: {e}"))?;
+
+        let references = program.references();
+        let variables = references.variables();
+        // The members not evaluated yet could time out, the expression checking the timeouts
+        // is executed once all the members have been evaluated
+        let short_circuit = !variables.contains(&TIMED_OUT);
+        let members: Vec<String> = variables
+            .into_iter()
+            .filter(|variable| self.members.contains_key(*variable))
+            .map(ToOwned::to_owned)
+            .collect();
+        let mut members = self.members_by_cost(members).into_iter().peekable();
+        loop {
+            let all_evaluated = members.peek().is_none();
+            if short_circuit || all_evaluated {
+                let timed_out: Vec<String> = self
+                    .members
+                    .keys()
+                    .filter(|name| responses.timed_out(name))
+                    .cloned()
+                    .collect();
+                context.add_variable_from_value(TIMED_OUT, timed_out);
+
+                // Executing the expression fails while it references a member not evaluated
+                // yet
+                match program.execute(&context) {
+                    Ok(Value::Bool(allowed)) => return Ok(allowed),
+                    Ok(other) if all_evaluated => {
+                        return Err(format!(
+                            "the expression returned {other:?} instead of a bool"
+                        ));
+                    }
+                    Err(e) if all_evaluated => return Err(e.to_string()),
+                    _ => {}
+                }
+            }
+
+            let name = members.next().expect("a member is left to evaluate");
+            let allowed = Self::member_allowed(&name, &self.members[&name], request, responses)?;
+            context.add_variable_from_value(name, allowed);
+        }
+    }
+}
+
+/// Returns the identifiers referenced by an expression, without duplicates. The Rhai
+/// expressions reference the members by calling them, or by passing their name to
+/// `timed_out`; the identifiers are returned in the order they appear. The CEL expressions
+/// reference the members as variables.
+fn expression_references(expression: &str, expression_language: ExpressionLanguage) -> Vec<String> {
+    let mut references = Vec::new();
+    match expression_language {
+        ExpressionLanguage::Rhai => {
+            // The expression has been validated at bootstrap, see `validate_settings`
+            let Ok(ast) = Engine::new().compile(expression) else {
+                return references;
+            };
+            ast.walk(&mut |path: &[ASTNode]| {
+                let call = match path.last() {
+                    Some(
+                        ASTNode::Expr(Expr::FnCall(call, _)) | ASTNode::Stmt(Stmt::FnCall(call, _)),
+                    ) => call,
+                    _ => return true,
+                };
+                // The operators are function calls too
+                if call.op_token.is_none() {
+                    let name = match call.args.first() {
+                        Some(Expr::StringConstant(name, _)) if call.name == TIMED_OUT => name,
+                        _ => &call.name,
+                    };
+                    references.push(name.to_string());
+                }
+                true
+            });
+        }
+        ExpressionLanguage::Cel => {
+            if let Ok(program) = Program::compile(expression) {
+                references = program
+                    .references()
+                    .variables()
+                    .into_iter()
+                    .map(ToOwned::to_owned)
+                    .collect();
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    references.retain(|name| seen.insert(name.clone()));
+    references
+}
+
+/// Sort the members by cost, the cheapest first. The members that have never been evaluated
+/// come first, to measure them; the ties keep the given order.
+fn sort_by_cost(names: &mut [String], cost: impl Fn(&str) -> Option<Duration>) {
+    names.sort_by_cached_key(|name| cost(name));
+}
+
+/// Evaluate a member of the group.
+///
+/// When the member is not allowed to mutate the request, a mutation is considered a rejection.
+///
+/// When the evaluation of the member times out, the member accepts or rejects the request
+/// according to its `on_timeout` setting.
+///
+/// The member cannot be evaluated when it cannot be rehydrated, or when its evaluation
+/// panics: this is reported as an error of the member, which fails the whole group.
+///
+/// The rejections of the members in monitor mode are logged and counted, then the member is
+/// considered as accepting the request.
+fn evaluate_member(
+    name: &str,
+    member: &PolicyGroupMemberEvaluator,
+    request: &ValidateRequest,
+    allowed_to_mutate: bool,
+) -> MemberEvaluation {
+    debug!(policy = name, "evaluate policy group member");
+    let start_time = Instant::now();
+    let start_epoch = policy_timeout::current_epoch();
+    let evaluation =
+        panic::catch_unwind(AssertUnwindSafe(|| -> Result<AdmissionResponse, String> {
+            let mut evaluator = member
+                .policy_evaluator_pre
+                .rehydrate(&member.eval_ctx)
+                .map_err(|e| format!("cannot rehydrate PolicyEvaluatorPre: {e}"))?;
+            Ok(evaluator.validate(request.clone(), &member.settings))
+        }))
+        .unwrap_or_else(|_| Err("the evaluation of the policy panicked".to_owned()));
+    let duration = start_time.elapsed();
+    member.cost.record(duration);
+
+    let response = match evaluation {
+        Ok(response) if response.patch.is_some() && !allowed_to_mutate => {
+            AdmissionResponse::reject(
+                response.uid,
+                "mutation is not allowed inside of policy group".to_owned(),
+                500,
+            )
+        }
+        Ok(response) => response,
+        Err(e) => {
+            let error = format!("policy {name}: {e}");
+            warn!(
+                policy_id = %member.eval_ctx.policy_id,
+                error,
+                "cannot evaluate policy group member"
+            );
+            return MemberEvaluation {
+                response: AdmissionResponse::reject(request.uid().to_owned(), error.clone(), 500),
+                duration,
+                timed_out: false,
+                error: Some(error),
+            };
+        }
+    };
+
+    let timed_out =
+        policy_timeout::is_timeout(&response, start_epoch, member.eval_ctx.epoch_deadline);
+    let response = if timed_out {
+        warn!(
+            policy_id = %member.eval_ctx.policy_id,
+            on_timeout = %member.on_timeout,
+            "policy group member evaluation timed out"
+        );
+        metrics::add_group_member_timeout(&metrics::GroupMemberTimeout {
+            policy_name: member.eval_ctx.policy_id.clone(),
+            on_timeout: member.on_timeout.to_string(),
+        });
+        match member.on_timeout {
+            OnTimeout::Allow => AdmissionResponse {
+                uid: response.uid,
+                allowed: true,
+                ..Default::default()
+            },
+            OnTimeout::Deny => AdmissionResponse::reject(
+                response.uid,
+                "the evaluation of the policy timed out".to_owned(),
+                500,
+            ),
+            OnTimeout::Error => response,
+        }
+    } else {
+        response
+    };
+
+    if !matches!(member.policy_mode, PolicyMode::Monitor) {
+        return MemberEvaluation {
+            response,
+            duration,
+            timed_out,
+            error: None,
+        };
+    }
+    if !response.allowed {
+        let message = response
+            .status
+            .as_ref()
+            .and_then(|status| status.message.clone());
+        info!(
+            policy_id = %member.eval_ctx.policy_id,
+            ?message,
+            "policy group member in monitor mode rejected the request"
+        );
+        metrics::add_monitored_group_member_rejection(&metrics::MonitoredGroupMemberRejection {
+            policy_name: member.eval_ctx.policy_id.clone(),
+        });
+    }
+
+    // Like the policies in monitor mode, the members in monitor mode do not mutate the request
+    MemberEvaluation {
+        response: AdmissionResponse {
+            uid: response.uid,
+            allowed: true,
+            ..Default::default()
+        },
+        duration,
+        timed_out,
+        error: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case::rhai(
+        ExpressionLanguage::Rhai,
+        r#"signed() && (!timed_out("slow") || slow())"#,
+        &["signed", "slow"]
+    )]
+    #[case::rhai_name_inside_of_another_one(ExpressionLanguage::Rhai, "unsigned()", &["unsigned"])]
+    #[case::cel(ExpressionLanguage::Cel, "signed && !unsigned", &["signed", "unsigned"])]
+    fn expression_references_are_parsed(
+        #[case] expression_language: ExpressionLanguage,
+        #[case] expression: &str,
+        #[case] expected: &[&str],
+    ) {
+        let mut references = expression_references(expression, expression_language);
+        if expression_language == ExpressionLanguage::Cel {
+            // The CEL variables are not ordered
+            references.sort();
+        }
+
+        assert_eq!(references, expected);
+    }
+
+    #[test]
+    fn members_are_evaluated_once() {
+        let responses = MemberResponses::default();
+
+        assert!(responses.claim("member"));
+        assert!(!responses.claim("member"));
+        responses.insert(
+            "member",
+            MemberEvaluation {
+                response: AdmissionResponse {
+                    uid: "uid".to_owned(),
+                    allowed: true,
+                    ..Default::default()
+                },
+                duration: Duration::ZERO,
+                timed_out: false,
+                error: None,
+            },
+        );
+        assert!(!responses.claim("member"));
+        assert_eq!(responses.wait_allowed("member", None), Ok(true));
+
+        responses.close();
+        assert!(!responses.claim("other"));
+    }
+
+    #[test]
+    fn member_cost_is_a_moving_average() {
+        let cost = MemberCost::default();
+        assert_eq!(cost.estimate(), None);
+
+        cost.record(Duration::from_millis(80));
+        assert_eq!(cost.estimate(), Some(Duration::from_millis(80)));
+        cost.record(Duration::from_millis(160));
+        assert_eq!(cost.estimate(), Some(Duration::from_millis(90)));
+        cost.record(Duration::from_millis(10));
+        assert_eq!(cost.estimate(), Some(Duration::from_millis(80)));
+    }
+
+    #[test]
+    fn members_are_sorted_by_cost() {
+        let costs = HashMap::from([
+            ("slow", Duration::from_millis(300)),
+            ("fast", Duration::from_millis(5)),
+            ("medium", Duration::from_millis(50)),
+        ]);
+        let mut names: Vec<String> = ["slow", "unknown", "fast", "medium", "other_unknown"]
+            .into_iter()
+            .map(ToOwned::to_owned)
+            .collect();
+
+        sort_by_cost(&mut names, |name| costs.get(name).copied());
+
+        assert_eq!(
+            names,
+            ["unknown", "other_unknown", "fast", "medium", "slow"]
+        );
+    }
+
+    #[test]
+    fn waiting_for_a_member_is_limited() {
+        let responses = MemberResponses::default();
+        assert!(responses.claim("member"));
+
+        let error = responses
+            .wait_allowed("member", Some(Duration::from_millis(10)))
+            .unwrap_err();
+
+        assert!(
+            error.contains("timed out waiting"),
+            "unexpected error: {error}"
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use json_patch::{Patch, PatchOperation};
+use policy_evaluator::{admission_request::AdmissionRequest, policy_evaluator::ValidateRequest};
+
+/// Applies the patches produced by the mutating members of a policy group, one after the
+/// other. Each member receives the object mutated by the previous ones, the patches are
+/// then combined into a single one.
+///
+/// A member is not allowed to modify a path modified by a previous member: the two
+/// patches would be in conflict, and the result would depend on the order of the members.
+pub(super) struct MutationChain {
+    /// The request received by the group, as JSON
+    original: serde_json::Value,
+    /// The request mutated by the members evaluated so far, as JSON
+    mutated: serde_json::Value,
+    /// The paths modified so far, together with the name of the member modifying them
+    modified_paths: Vec<(String, String)>,
+}
+
+impl MutationChain {
+    pub(super) fn new(request: &AdmissionRequest) -> Result<Self, String> {
+        let original = serde_json::to_value(request)
+            .map_err(|e| format!("cannot convert the request: {e}"))?;
+
+        Ok(Self {
+            mutated: original.clone(),
+            original,
+            modified_paths: Vec::new(),
+        })
+    }
+
+    /// Returns the request mutated by the members evaluated so far
+    pub(super) fn request(&self) -> Result<ValidateRequest, String> {
+        serde_json::from_value::<AdmissionRequest>(self.mutated.clone())
+            .map(|request| ValidateRequest::AdmissionRequest(Box::new(request)))
+            .map_err(|e| format!("cannot convert the mutated request: {e}"))
+    }
+
+    /// Apply the base64 encoded JSONPatch produced by the given member
+    pub(super) fn apply(&mut self, member: &str, patch: &str) -> Result<(), String> {
+        let patch = STANDARD
+            .decode(patch)
+            .map_err(|e| format!("cannot decode the patch of policy {member}: {e}"))?;
+        let patch: Patch = serde_json::from_slice(&patch)
+            .map_err(|e| format!("cannot parse the patch of policy {member}: {e}"))?;
+
+        let mut modified_paths = Vec::new();
+        for path in patch.0.iter().flat_map(modified_paths_of) {
+            if let Some((other_path, other_member)) =
+                self.modified_paths
+                    .iter()
+                    .find(|(other_path, other_member)| {
+                        other_member != member && overlap(&path, other_path)
+                    })
+            {
+                return Err(format!(
+                    "policy {member} modifies {path}, which conflicts with the modification of {other_path} made by policy {other_member}"
+                ));
+            }
+            modified_paths.push((path, member.to_owned()));
+        }
+
+        json_patch::patch(&mut self.mutated["object"], &patch.0)
+            .map_err(|e| format!("cannot apply the patch of policy {member}: {e}"))?;
+        self.modified_paths.extend(modified_paths);
+
+        Ok(())
+    }
+
+    /// Returns the base64 encoded JSONPatch combining the patches applied so far, `None`
+    /// when the object has not been modified
+    pub(super) fn patch(&self) -> Result<Option<String>, String> {
+        let patch = json_patch::diff(&self.original["object"], &self.mutated["object"]);
+        if patch.0.is_empty() {
+            return Ok(None);
+        }
+
+        serde_json::to_vec(&patch)
+            .map(|patch| Some(STANDARD.encode(patch)))
+            .map_err(|e| format!("cannot serialize the combined patch: {e}"))
+    }
+}
+
+/// Returns the paths modified by the given operation
+fn modified_paths_of(operation: &PatchOperation) -> Vec<String> {
+    match operation {
+        PatchOperation::Add(operation) => vec![operation.path.to_string()],
+        PatchOperation::Remove(operation) => vec![operation.path.to_string()],
+        PatchOperation::Replace(operation) => vec![operation.path.to_string()],
+        PatchOperation::Move(operation) => {
+            vec![operation.from.to_string(), operation.path.to_string()]
+        }
+        PatchOperation::Copy(operation) => vec![operation.path.to_string()],
+        PatchOperation::Test(_) => Vec::new(),
+    }
+}
+
+/// Returns `true` when one of the JSON pointers is the same, or a parent, of the other one
+fn overlap(a: &str, b: &str) -> bool {
+    a == b || a.starts_with(&format!("{b}/")) || b.starts_with(&format!("{a}/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::build_admission_review_request;
+    use rstest::*;
+    use serde_json::json;
+
+    fn encode(patch: serde_json::Value) -> String {
+        STANDARD.encode(serde_json::to_vec(&patch).unwrap())
+    }
+
+    fn decode(patch: &str) -> serde_json::Value {
+        serde_json::from_slice(&STANDARD.decode(patch).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn patches_are_applied_in_order_and_combined() {
+        let request = build_admission_review_request().request;
+        let mut chain = MutationChain::new(&request).unwrap();
+
+        chain
+            .apply(
+                "add_metadata",
+                &encode(json!([
+                    { "op": "add", "path": "/metadata", "value": { "labels": { "team": "a" } } }
+                ])),
+            )
+            .unwrap();
+        let ValidateRequest::AdmissionRequest(mutated) = chain.request().unwrap() else {
+            panic!("expected an admission request");
+        };
+        let mutated = serde_json::to_value(mutated).unwrap();
+        assert_eq!(mutated["object"]["metadata"]["labels"]["team"], "a");
+
+        chain
+            .apply(
+                "add_spec",
+                &encode(json!([
+                    { "op": "add", "path": "/spec", "value": { "replicas": 3 } }
+                ])),
+            )
+            .unwrap();
+
+        let patch: Patch =
+            serde_json::from_value(decode(&chain.patch().unwrap().unwrap())).unwrap();
+        let mut object = serde_json::to_value(&request).unwrap()["object"].clone();
+        json_patch::patch(&mut object, &patch.0).unwrap();
+        assert_eq!(object["metadata"]["labels"]["team"], "a");
+        assert_eq!(object["spec"]["replicas"], 3);
+    }
+
+    #[rstest]
+    #[case::same_path("/metadata")]
+    #[case::child_path("/metadata/labels")]
+    fn conflicting_patches_are_rejected(#[case] path: &str) {
+        let request = build_admission_review_request().request;
+        let mut chain = MutationChain::new(&request).unwrap();
+        chain
+            .apply(
+                "first",
+                &encode(json!([
+                    { "op": "add", "path": "/metadata", "value": { "labels": { "team": "a" } } }
+                ])),
+            )
+            .unwrap();
+
+        let error = chain
+            .apply(
+                "second",
+                &encode(json!([{ "op": "add", "path": path, "value": {} }])),
+            )
+            .unwrap_err();
+
+        assert!(error.contains("conflicts"), "unexpected error: {error}");
+    }
+
+    #[test]
+    fn no_patch_when_the_object_is_not_modified() {
+        let request = build_admission_review_request().request;
+        let chain = MutationChain::new(&request).unwrap();
+
+        assert_eq!(chain.patch().unwrap(), None);
+    }
+}
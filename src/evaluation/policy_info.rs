@@ -0,0 +1,49 @@
+use std::collections::BTreeSet;
+
+use policy_evaluator::policy_metadata::ContextAwareResource;
+use serde::Serialize;
+
+/// Describes a policy loaded by the Policy Server, together with its status.
+/// This is used to report the policies loaded by a running instance.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PolicyInfo {
+    /// The ID of the policy
+    pub(crate) id: String,
+    /// The URL of the Wasm module, this is not set for policy groups
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) module: Option<String>,
+    /// The digest of the precompiled Wasm module, this is not set for policy groups
+    /// and for the policies that could not be loaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) module_digest: Option<String>,
+    /// The mode of the policy, this is not set for the policies that could not be loaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) policy_mode: Option<String>,
+    /// Whether the policy is allowed to mutate the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) allowed_to_mutate: Option<bool>,
+    /// The list of Kubernetes resources the policy is allowed to access
+    pub(crate) context_aware_resources: BTreeSet<ContextAwareResource>,
+    /// The number of seconds after which the evaluation of the policy is interrupted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) timeout_eval_seconds: Option<u64>,
+    /// Details about the policy group, set only when the policy is a group
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) group: Option<PolicyGroupInfo>,
+    /// The error that occurred while loading the policy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) initialization_error: Option<String>,
+}
+
+/// Describes a policy group loaded by the Policy Server
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PolicyGroupInfo {
+    /// The expression used to evaluate the group
+    pub(crate) expression: String,
+    /// The message returned when the group rejects a request
+    pub(crate) message: String,
+    /// The policies that are part of the group
+    pub(crate) members: Vec<PolicyInfo>,
+}
@@ -0,0 +1,79 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use policy_evaluator::admission_response::AdmissionResponse;
+
+/// Number of times the epoch of the wasmtime engine has been incremented by the policy
+/// timeout protection. The engine does not expose its epoch, this counter follows it.
+static EPOCH: AtomicU64 = AtomicU64::new(0);
+
+/// Must be called right after the epoch of the wasmtime engine has been incremented.
+pub(crate) fn increment_epoch() {
+    EPOCH.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Returns the current epoch, to be read right before starting an evaluation.
+pub(crate) fn current_epoch() -> u64 {
+    EPOCH.load(Ordering::SeqCst)
+}
+
+/// Returns `true` when the evaluation that started at `start_epoch` has been interrupted
+/// because it exceeded its `epoch_deadline`.
+///
+/// wasmtime interrupts the evaluation once the epoch of the engine has been incremented
+/// `epoch_deadline` times, the policy then rejects the request with a 500 error. A 500 error
+/// returned before the deadline is reached is a failure of the policy, like a Wasm trap.
+pub(crate) fn is_timeout(
+    response: &AdmissionResponse,
+    start_epoch: u64,
+    epoch_deadline: Option<u64>,
+) -> bool {
+    deadline_reached(current_epoch(), start_epoch, epoch_deadline)
+        && !response.allowed
+        && response
+            .status
+            .as_ref()
+            .is_some_and(|status| status.code == Some(500))
+}
+
+fn deadline_reached(epoch: u64, start_epoch: u64, epoch_deadline: Option<u64>) -> bool {
+    epoch_deadline.is_some_and(|deadline| epoch.saturating_sub(start_epoch) >= deadline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case::reached(12, 10, Some(2), true)]
+    #[case::exceeded(13, 10, Some(2), true)]
+    #[case::not_reached(11, 10, Some(2), false)]
+    #[case::no_deadline(100, 10, None, false)]
+    fn deadline_is_detected(
+        #[case] epoch: u64,
+        #[case] start_epoch: u64,
+        #[case] epoch_deadline: Option<u64>,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(
+            deadline_reached(epoch, start_epoch, epoch_deadline),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::failure(AdmissionResponse::reject("uid".to_owned(), "error".to_owned(), 500), true)]
+    #[case::rejection(AdmissionResponse::reject("uid".to_owned(), "error".to_owned(), 400), false)]
+    #[case::allowed(
+        AdmissionResponse {
+            uid: "uid".to_owned(),
+            allowed: true,
+            ..Default::default()
+        },
+        false
+    )]
+    fn timeouts_are_detected(#[case] response: AdmissionResponse, #[case] expected: bool) {
+        // The deadline of zero epochs is always reached
+        assert_eq!(is_timeout(&response, current_epoch(), Some(0)), expected);
+    }
+}
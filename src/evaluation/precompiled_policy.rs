@@ -5,7 +5,14 @@ use policy_evaluator::{
 };
 use semver::{BuildMetadata, Prerelease, Version};
 use sha2::{Digest, Sha256};
-use std::{collections::HashMap, fs, path::Path, vec::Vec};
+use std::{
+    collections::HashMap,
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::Path,
+    vec::Vec,
+};
+use tracing::{debug, warn};
 
 lazy_static! {
     static ref KUBEWARDEN_VERSION: Version = {
@@ -42,8 +49,16 @@ pub(crate) struct PrecompiledPolicy {
 }
 
 impl PrecompiledPolicy {
-    /// Load a WebAssembly module from the disk and compiles it
-    pub fn new(engine: &wasmtime::Engine, wasm_module_path: &Path) -> Result<Self> {
+    /// Load a WebAssembly module from the disk and compiles it.
+    ///
+    /// When `cache_dir` is provided, the compilation is skipped if the directory already holds
+    /// the result of a previous compilation of the same module, done with a compatible
+    /// `wasmtime::Engine`. Otherwise the result of the compilation is stored inside of it.
+    pub fn new(
+        engine: &wasmtime::Engine,
+        wasm_module_path: &Path,
+        cache_dir: Option<&Path>,
+    ) -> Result<Self> {
         let policy_contents = fs::read(wasm_module_path)?;
         let policy_metadata = Metadata::from_contents(&policy_contents)?;
         let metadata = policy_metadata.unwrap_or_default();
@@ -52,7 +67,10 @@ impl PrecompiledPolicy {
 
         has_valid_protocol_version(&metadata)?;
 
-        let precompiled_module = engine.precompile_module(&policy_contents)?;
+        let precompiled_module = match cache_dir {
+            Some(cache_dir) => load_or_precompile_module(engine, cache_dir, &policy_contents)?,
+            None => engine.precompile_module(&policy_contents)?,
+        };
 
         let mut hasher = Sha256::new();
         hasher.update(&precompiled_module);
@@ -73,6 +91,100 @@ impl PrecompiledPolicy {
 /// Errors are stored and will be reported to the user in the API response.
 pub(crate) type PrecompiledPolicies = HashMap<String, Result<PrecompiledPolicy>>;
 
+/// Look for the precompiled version of the given Wasm module inside of the cache directory.
+/// When it is not found, the module is compiled and the result is written to the cache.
+///
+/// The entries of the cache are indexed by the sha256 digest of the Wasm module and by a
+/// fingerprint of the `wasmtime::Engine` configuration, hence a module compiled with an
+/// incompatible engine is never loaded.
+///
+/// Each entry is made by two files: the precompiled module and its sha256 digest. The
+/// digest is checked before using the entry, to ensure it has not been corrupted.
+fn load_or_precompile_module(
+    engine: &wasmtime::Engine,
+    cache_dir: &Path,
+    wasm_module: &[u8],
+) -> Result<Vec<u8>> {
+    let mut hasher = Sha256::new();
+    hasher.update(wasm_module);
+    let wasm_module_digest = hasher.finalize();
+
+    let mut engine_hasher = DefaultHasher::new();
+    engine
+        .precompile_compatibility_hash()
+        .hash(&mut engine_hasher);
+    let engine_fingerprint = engine_hasher.finish();
+
+    let cache_key = format!("{wasm_module_digest:x}-{engine_fingerprint:016x}");
+    let precompiled_module_path = cache_dir.join(format!("{cache_key}.cwasm"));
+    let precompiled_module_digest_path = cache_dir.join(format!("{cache_key}.sha256"));
+
+    if let (Ok(precompiled_module), Ok(expected_digest)) = (
+        fs::read(&precompiled_module_path),
+        fs::read_to_string(&precompiled_module_digest_path),
+    ) {
+        let mut hasher = Sha256::new();
+        hasher.update(&precompiled_module);
+        let digest = format!("{:x}", hasher.finalize());
+
+        if digest == expected_digest.trim()
+            && engine.detect_precompiled(&precompiled_module) == Some(wasmtime::Precompiled::Module)
+        {
+            debug!(
+                ?precompiled_module_path,
+                "precompiled module loaded from cache"
+            );
+            return Ok(precompiled_module);
+        }
+        warn!(
+            ?precompiled_module_path,
+            "invalid precompiled module found inside of the cache, compiling it again"
+        );
+    }
+
+    let precompiled_module = engine.precompile_module(wasm_module)?;
+
+    if let Err(e) = write_cache_entry(
+        cache_dir,
+        &precompiled_module_path,
+        &precompiled_module_digest_path,
+        &precompiled_module,
+    ) {
+        warn!(error = %e, "cannot store precompiled module inside of the cache");
+    }
+
+    Ok(precompiled_module)
+}
+
+/// Write a new entry of the precompiled modules cache. The files are first written with a
+/// temporary name, then renamed. This prevents partially written entries from being used.
+fn write_cache_entry(
+    cache_dir: &Path,
+    precompiled_module_path: &Path,
+    precompiled_module_digest_path: &Path,
+    precompiled_module: &[u8],
+) -> Result<()> {
+    fs::create_dir_all(cache_dir)?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(precompiled_module);
+    let digest = format!("{:x}", hasher.finalize());
+
+    let tmp_precompiled_module_path = precompiled_module_path.with_extension("cwasm.tmp");
+    let tmp_precompiled_module_digest_path =
+        precompiled_module_digest_path.with_extension("sha256.tmp");
+
+    fs::write(&tmp_precompiled_module_path, precompiled_module)?;
+    fs::write(&tmp_precompiled_module_digest_path, digest)?;
+    fs::rename(&tmp_precompiled_module_path, precompiled_module_path)?;
+    fs::rename(
+        &tmp_precompiled_module_digest_path,
+        precompiled_module_digest_path,
+    )?;
+
+    Ok(())
+}
+
 /// Check if policy server version is compatible with  minimum kubewarden
 /// version required by the policy
 fn has_minimum_kubewarden_version(metadata: &Metadata) -> Result<()> {
@@ -167,6 +279,36 @@ mod tests {
         assert!(has_minimum_kubewarden_version(&metadata).is_ok())
     }
 
+    #[test]
+    fn precompiled_module_is_cached() {
+        let engine = wasmtime::Engine::default();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let wasm_module = include_bytes!("../../tests/data/gatekeeper_always_happy_policy.wasm");
+
+        let precompiled_module =
+            load_or_precompile_module(&engine, cache_dir.path(), wasm_module).unwrap();
+
+        let cached_modules: Vec<_> = fs::read_dir(cache_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "cwasm"))
+            .collect();
+        assert_eq!(cached_modules.len(), 1);
+        assert_eq!(fs::read(&cached_modules[0]).unwrap(), precompiled_module);
+
+        // the cached module is used
+        let cached_module =
+            load_or_precompile_module(&engine, cache_dir.path(), wasm_module).unwrap();
+        assert_eq!(cached_module, precompiled_module);
+
+        // a corrupted entry is ignored and replaced
+        fs::write(&cached_modules[0], b"corrupted").unwrap();
+        let recompiled_module =
+            load_or_precompile_module(&engine, cache_dir.path(), wasm_module).unwrap();
+        assert!(engine.detect_precompiled(&recompiled_module).is_some());
+        assert_eq!(fs::read(&cached_modules[0]).unwrap(), recompiled_module);
+    }
+
     #[rstest]
     #[case(Metadata {
         execution_mode: PolicyExecutionMode::KubewardenWapc,
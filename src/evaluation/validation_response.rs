@@ -0,0 +1,55 @@
+use policy_evaluator::admission_response::AdmissionResponse;
+
+/// Why the evaluation of a policy failed. Unlike a rejection, a failure is not the verdict of
+/// the policy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FailureKind {
+    /// The settings of the policy have been rejected by the policy
+    SettingsInvalid,
+    /// The policy could not be initialized
+    Initialization,
+    /// The evaluation took longer than the time allowed
+    Timeout,
+    /// The Wasm module of the policy could not be instantiated, or crashed
+    WebAssembly,
+    /// Any other failure, like a policy group that cannot combine the responses of its members
+    Internal,
+}
+
+impl FailureKind {
+    /// Returns `true` when the policy could not be initialized: the policy cannot evaluate
+    /// any request
+    pub(crate) fn is_initialization(&self) -> bool {
+        matches!(
+            self,
+            FailureKind::SettingsInvalid | FailureKind::Initialization
+        )
+    }
+}
+
+/// The response given by a policy to a request
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ValidationResponse {
+    pub(crate) response: AdmissionResponse,
+    /// Set when the evaluation failed, `response` then rejects the request and reports the
+    /// failure
+    pub(crate) failure: Option<FailureKind>,
+}
+
+impl ValidationResponse {
+    /// The verdict of the policy
+    pub(crate) fn verdict(response: AdmissionResponse) -> Self {
+        Self {
+            response,
+            failure: None,
+        }
+    }
+
+    /// The evaluation of the policy failed
+    pub(crate) fn failure(uid: String, kind: FailureKind, message: String) -> Self {
+        Self {
+            response: AdmissionResponse::reject(uid, message, 500),
+            failure: Some(kind),
+        }
+    }
+}
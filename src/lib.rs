@@ -1,5 +1,7 @@
 mod certs;
 mod evaluation;
+mod policies_loader;
+mod policies_watcher;
 mod policy_downloader;
 
 #[cfg(test)]
@@ -10,11 +12,12 @@ mod cli;
 
 pub mod api;
 pub mod config;
+pub mod dry_run;
 pub mod metrics;
 pub mod profiling;
 pub mod tracing;
 
-use ::tracing::{Level, debug, info, trace, warn};
+use ::tracing::{Level, error, info, trace, warn};
 use anyhow::{Result, anyhow};
 use axum::{
     Router,
@@ -22,7 +25,8 @@ use axum::{
 };
 use axum_server::tls_rustls::RustlsConfig;
 use certs::create_tls_config_and_watch_certificate_changes;
-use evaluation::EvaluationEnvironmentBuilder;
+use policies_loader::PoliciesLoader;
+use policies_watcher::watch_policies_file_changes;
 use policy_evaluator::{
     callback_handler::{CallbackHandler, CallbackHandlerBuilder},
     kube,
@@ -30,7 +34,6 @@ use policy_evaluator::{
     wasmtime,
 };
 use profiling::activate_memory_profiling;
-use rayon::prelude::*;
 use std::{fs, net::SocketAddr, sync::Arc};
 use tokio::{
     sync::{Notify, Semaphore, oneshot},
@@ -39,12 +42,14 @@ use tokio::{
 use tower_http::trace::{self, TraceLayer};
 
 use crate::api::handlers::{
-    audit_handler, pprof_get_cpu, pprof_get_heap, readiness_handler, validate_handler,
+    audit_handler, liveness_handler, policies_handler, policy_handler, pprof_get_cpu,
+    pprof_get_heap, readiness_handler, validate_batch_handler, validate_handler,
     validate_raw_handler,
 };
-use crate::api::state::ApiServerState;
-use crate::evaluation::precompiled_policy::{PrecompiledPolicies, PrecompiledPolicy};
-use crate::policy_downloader::{Downloader, FetchedPolicies};
+use crate::api::state::{ApiServerState, ReadinessProbeSettings};
+use crate::evaluation::always_accepted_namespaces::AlwaysAcceptedNamespaces;
+use crate::evaluation::policy_timeout;
+use crate::policy_downloader::Downloader;
 use config::Config;
 
 use tikv_jemallocator::Jemalloc;
@@ -61,6 +66,7 @@ pub static malloc_conf: &[u8] = b"background_thread:true,tcache_max:4096,dirty_d
 pub struct PolicyServer {
     router: Router,
     readiness_probe_router: Router,
+    state: Arc<ApiServerState>,
     callback_handler: CallbackHandler,
     callback_handler_shutdown_channel_tx: oneshot::Sender<()>,
     addr: SocketAddr,
@@ -70,145 +76,41 @@ pub struct PolicyServer {
 
 impl PolicyServer {
     pub async fn new_from_config(config: Config) -> Result<Self> {
-        // This is a channel used to stop the tokio task that is run
-        // inside of the CallbackHandler
-        let (callback_handler_shutdown_channel_tx, callback_handler_shutdown_channel_rx) =
-            oneshot::channel();
-
-        let sigstore_trust_root = match create_sigstore_trustroot(&config).await {
-            Ok(trust_root) => Some(trust_root),
-            Err(e) => {
-                // Do not exit, only policies making use of sigstore's keyless/certificate based signatures will fail
-                // There are good chances everything is going to work fine in the majority of cases
-                warn!(
-                    "Cannot create Sigstore trust root, verification relying on Rekor and Fulcio will fail"
-                );
-                // Only log the error if the log level is set to trace. This is to avoid
-                // spamming the logs with errors that are not relevant to most users
-                trace!(?e);
-                None
-            }
-        };
-
-        let mut callback_handler_builder =
-            CallbackHandlerBuilder::new(callback_handler_shutdown_channel_rx)
-                .registry_config(config.sources.clone())
-                .trust_root(sigstore_trust_root.clone());
-
-        let kube_client: Option<kube::Client> = match kube::Client::try_default().await {
-            Ok(client) => Some(client),
-            Err(e) => {
-                // We cannot rely on `tracing` yet, because the tracing system has not
-                // been initialized yet
-                eprintln!("Cannot connect to Kubernetes cluster: {e}");
-                None
-            }
-        };
-
-        match kube_client {
-            Some(client) => {
-                callback_handler_builder = callback_handler_builder.kube_client(client);
-            }
-            None => {
-                if config.ignore_kubernetes_connection_failure {
-                    // We cannot rely on `tracing` yet, because the tracing system has not
-                    // been initialized yet
-                    eprintln!(
-                        "Cannot connect to Kubernetes, context aware policies will not work properly"
-                    );
-                } else {
-                    return Err(anyhow!(
-                        "Cannot connect to Kubernetes, context aware policies would not work properly"
-                    ));
-                }
-            }
-        };
-
-        let callback_handler = callback_handler_builder.build().await?;
-        let callback_sender_channel = callback_handler.sender_channel();
-
-        // Download policies
-        let downloader_sigstore_trust_root = if config.verification_config.is_some() {
-            sigstore_trust_root.clone()
-        } else {
-            None
-        };
-        let mut downloader =
-            Downloader::new(config.sources.clone(), downloader_sigstore_trust_root).await?;
-
-        let fetched_policies = downloader
-            .download_policies(
-                &config.policies,
-                &config.policies_download_dir,
-                config.verification_config.as_ref(),
-            )
-            .await;
-
-        let mut wasmtime_config = wasmtime::Config::new();
-
-        let any_policy_has_timeout = config.policies.values().any(|policy| match policy {
-            config::PolicyOrPolicyGroup::Policy {
-                timeout_eval_seconds,
-                ..
-            } => timeout_eval_seconds.is_some(),
-            config::PolicyOrPolicyGroup::PolicyGroup { policies, .. } => policies
-                .values()
-                .any(|member| member.timeout_eval_seconds.is_some()),
-        });
-        if config.policy_evaluation_limit_seconds.is_some() || any_policy_has_timeout {
-            wasmtime_config.epoch_interruption(true);
-        }
-
-        let engine = wasmtime::Engine::new(&wasmtime_config)?;
-        let precompiled_policies = precompile_policies(&engine, &fetched_policies);
-
-        if !config.continue_on_errors {
-            for result in precompiled_policies.values() {
-                if let Err(error) = result {
-                    return Err(anyhow!(error.to_string()));
-                }
+        let PolicyEvaluationComponents {
+            callback_handler,
+            callback_handler_shutdown_channel_tx,
+            engine,
+            mut policies_loader,
+        } = PolicyEvaluationComponents::new(&config).await?;
+        let evaluation_environment = policies_loader.load(&config.policies).await?;
+
+        start_policy_timeout_protection(&engine, config.policy_evaluation_limit_seconds);
+
+        let state = Arc::new(ApiServerState::new(
+            Semaphore::new(config.pool_size),
+            evaluation_environment,
+            ReadinessProbeSettings {
+                max_policy_errors: config.readiness_max_policy_errors,
+                fail_on_reload: config.readiness_fail_on_reload,
+            },
+            config.redact_error_details,
+        ));
+
+        if config.enable_policies_hot_reload {
+            if config.policies_paths.is_empty() {
+                warn!("policies hot reload requires the policies to be loaded from a file");
+            } else {
+                info!(policies_paths = ?config.policies_paths, "policies hot reload is enabled");
+                watch_policies_file_changes(
+                    config.policies_paths.clone(),
+                    config.settings_interpolation.clone(),
+                    config.policies.clone(),
+                    policies_loader,
+                    state.clone(),
+                )?;
             }
         }
 
-        let mut evaluation_environment_builder = EvaluationEnvironmentBuilder::new(
-            &engine,
-            &precompiled_policies,
-            callback_sender_channel.clone(),
-        )
-        .with_continue_on_errors(config.continue_on_errors);
-        if let Some(namespace) = config.always_accept_admission_reviews_on_namespace {
-            evaluation_environment_builder = evaluation_environment_builder
-                .with_always_accept_admission_reviews_on_namespace(namespace);
-        }
-        if let Some(limit) = config.policy_evaluation_limit_seconds {
-            evaluation_environment_builder =
-                evaluation_environment_builder.with_global_policy_evaluation_limit_seconds(limit);
-        }
-        let evaluation_environment = evaluation_environment_builder.build(&config.policies)?;
-
-        if let Some(limit) = config.policy_evaluation_limit_seconds {
-            info!(
-                execution_limit_seconds = limit,
-                "policy timeout protection is enabled"
-            );
-
-            let engine = engine.clone();
-            tokio::spawn(async move {
-                let mut interval = time::interval(time::Duration::from_secs(1));
-                loop {
-                    interval.tick().await;
-                    engine.increment_epoch();
-                }
-            });
-        } else {
-            info!("policy timeout protection is disabled");
-        }
-
-        let state = Arc::new(ApiServerState {
-            semaphore: Semaphore::new(config.pool_size),
-            evaluation_environment: Arc::new(evaluation_environment),
-        });
-
         let tls_config = if let Some(tls_config) = config.tls_config {
             Some(create_tls_config_and_watch_certificate_changes(tls_config).await?)
         } else {
@@ -217,8 +119,11 @@ impl PolicyServer {
 
         let mut router = Router::new()
             .route("/audit/{policy_id}", post(audit_handler))
+            .route("/policies", get(policies_handler))
+            .route("/policies/{policy_id}", get(policy_handler))
             .route("/validate/{policy_id}", post(validate_handler))
             .route("/validate_raw/{policy_id}", post(validate_raw_handler))
+            .route("/validate_batch", post(validate_batch_handler))
             .with_state(state.clone())
             .layer(
                 TraceLayer::new_for_http()
@@ -235,11 +140,15 @@ impl PolicyServer {
             router = Router::new().merge(router).merge(pprof_router);
         }
 
-        let readiness_probe_router = Router::new().route("/readiness", get(readiness_handler));
+        let readiness_probe_router = Router::new()
+            .route("/readiness", get(readiness_handler))
+            .route("/livez", get(liveness_handler))
+            .with_state(state.clone());
 
         Ok(Self {
             router,
             readiness_probe_router,
+            state,
             callback_handler,
             callback_handler_shutdown_channel_tx,
             addr: config.addr,
@@ -252,10 +161,16 @@ impl PolicyServer {
         let notify = Notify::new();
 
         let mut callback_handler = self.callback_handler;
+        let state = self.state.clone();
         let callback_handler = tokio::spawn(async move {
             info!(status = "init", "CallbackHandler task");
-            callback_handler.loop_eval().await;
-            info!(status = "exit", "CallbackHandler task");
+            // The loop is run inside of its own task to detect when it panics
+            let result = tokio::spawn(async move { callback_handler.loop_eval().await }).await;
+            state.set_callback_handler_stopped();
+            match result {
+                Ok(()) => info!(status = "exit", "CallbackHandler task"),
+                Err(e) => error!(error = %e, "CallbackHandler task failed"),
+            }
         });
 
         let api_server = async {
@@ -295,28 +210,168 @@ impl PolicyServer {
     pub fn router(&self) -> Router {
         self.router.clone()
     }
+
+    pub fn readiness_probe_router(&self) -> Router {
+        self.readiness_probe_router.clone()
+    }
 }
 
-fn precompile_policies(
-    engine: &wasmtime::Engine,
-    fetched_policies: &FetchedPolicies,
-) -> PrecompiledPolicies {
-    debug!(
-        wasm_modules_count = fetched_policies.len(),
-        "instantiating wasmtime::Module objects"
-    );
-
-    fetched_policies
-        .par_iter()
-        .map(|(policy_url, fetched_policy)| match fetched_policy {
-            Ok(policy) => {
-                let precompiled_policy = PrecompiledPolicy::new(engine, policy);
-                debug!(?policy_url, "module compiled");
-                (policy_url.clone(), precompiled_policy)
+/// The components required to evaluate the policies. These are shared by the server
+/// and by the subcommands that evaluate the policies without starting it.
+pub(crate) struct PolicyEvaluationComponents {
+    pub(crate) callback_handler: CallbackHandler,
+    pub(crate) callback_handler_shutdown_channel_tx: oneshot::Sender<()>,
+    pub(crate) engine: wasmtime::Engine,
+    pub(crate) policies_loader: PoliciesLoader,
+}
+
+impl PolicyEvaluationComponents {
+    pub(crate) async fn new(config: &Config) -> Result<Self> {
+        // This is a channel used to stop the tokio task that is run
+        // inside of the CallbackHandler
+        let (callback_handler_shutdown_channel_tx, callback_handler_shutdown_channel_rx) =
+            oneshot::channel();
+
+        let sigstore_trust_root = match create_sigstore_trustroot(config).await {
+            Ok(trust_root) => Some(trust_root),
+            Err(e) => {
+                // Do not exit, only policies making use of sigstore's keyless/certificate based signatures will fail
+                // There are good chances everything is going to work fine in the majority of cases
+                warn!(
+                    "Cannot create Sigstore trust root, verification relying on Rekor and Fulcio will fail"
+                );
+                // Only log the error if the log level is set to trace. This is to avoid
+                // spamming the logs with errors that are not relevant to most users
+                trace!(?e);
+                None
+            }
+        };
+
+        let mut callback_handler_builder =
+            CallbackHandlerBuilder::new(callback_handler_shutdown_channel_rx)
+                .registry_config(config.sources.clone())
+                .trust_root(sigstore_trust_root.clone());
+
+        let kube_client: Option<kube::Client> = match kube::Client::try_default().await {
+            Ok(client) => Some(client),
+            Err(e) => {
+                // We cannot rely on `tracing` yet, because the tracing system has not
+                // been initialized yet
+                eprintln!("Cannot connect to Kubernetes cluster: {e}");
+                None
+            }
+        };
+
+        let always_accepted_namespaces =
+            AlwaysAcceptedNamespaces::new(&config.always_accept_admission_reviews_on_namespaces)?;
+        if let Some(label_selector) = &config.always_accept_admission_reviews_on_namespace_selector
+        {
+            match &kube_client {
+                Some(client) => {
+                    always_accepted_namespaces
+                        .watch_label_selector(client.clone(), label_selector.to_owned())
+                        .await?;
+                }
+                None => {
+                    // We cannot rely on `tracing` yet, because the tracing system has not
+                    // been initialized yet
+                    eprintln!(
+                        "Cannot connect to Kubernetes, the namespaces matching the selector {label_selector} will not be always accepted"
+                    );
+                }
+            }
+        }
+
+        match kube_client {
+            Some(client) => {
+                callback_handler_builder = callback_handler_builder.kube_client(client);
+            }
+            None => {
+                if config.ignore_kubernetes_connection_failure {
+                    // We cannot rely on `tracing` yet, because the tracing system has not
+                    // been initialized yet
+                    eprintln!(
+                        "Cannot connect to Kubernetes, context aware policies will not work properly"
+                    );
+                } else {
+                    return Err(anyhow!(
+                        "Cannot connect to Kubernetes, context aware policies would not work properly"
+                    ));
+                }
             }
-            Err(error) => (policy_url.clone(), Err(anyhow!(error.to_string()))),
+        };
+
+        let callback_handler = callback_handler_builder.build().await?;
+        let callback_sender_channel = callback_handler.sender_channel();
+
+        // Download policies
+        let downloader_sigstore_trust_root = if config.verification_config.is_some() {
+            sigstore_trust_root.clone()
+        } else {
+            None
+        };
+        let downloader =
+            Downloader::new(config.sources.clone(), downloader_sigstore_trust_root).await?;
+
+        let mut wasmtime_config = wasmtime::Config::new();
+
+        let any_policy_has_timeout = config.policies.values().any(|policy| match policy {
+            config::PolicyOrPolicyGroup::Policy {
+                timeout_eval_seconds,
+                ..
+            } => timeout_eval_seconds.is_some(),
+            config::PolicyOrPolicyGroup::PolicyGroup { policies, .. } => policies
+                .values()
+                .any(|member| member.timeout_eval_seconds.is_some()),
+        });
+        // When hot reload is enabled, the policies loaded later on could define
+        // a timeout too
+        if config.policy_evaluation_limit_seconds.is_some()
+            || any_policy_has_timeout
+            || config.enable_policies_hot_reload
+        {
+            wasmtime_config.epoch_interruption(true);
+        }
+
+        let engine = wasmtime::Engine::new(&wasmtime_config)?;
+
+        let policies_loader = PoliciesLoader::new(
+            engine.clone(),
+            downloader,
+            callback_sender_channel.clone(),
+            always_accepted_namespaces,
+            config,
+        );
+
+        Ok(Self {
+            callback_handler,
+            callback_handler_shutdown_channel_tx,
+            engine,
+            policies_loader,
         })
-        .collect()
+    }
+}
+
+/// Enable the interruption of the policy evaluations that take longer than `limit` seconds
+pub(crate) fn start_policy_timeout_protection(engine: &wasmtime::Engine, limit: Option<u64>) {
+    if let Some(limit) = limit {
+        info!(
+            execution_limit_seconds = limit,
+            "policy timeout protection is enabled"
+        );
+
+        let engine = engine.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(time::Duration::from_secs(1));
+            loop {
+                interval.tick().await;
+                engine.increment_epoch();
+                policy_timeout::increment_epoch();
+            }
+        });
+    } else {
+        info!("policy timeout protection is disabled");
+    }
 }
 
 async fn create_sigstore_trustroot(config: &Config) -> Result<Arc<SigstoreTrustRoot>> {
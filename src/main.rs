@@ -1,13 +1,16 @@
 mod cli;
 
+use std::env;
 use std::fs;
 use std::io::prelude::*;
+use std::path::PathBuf;
 
 use ::tracing::info;
 use anyhow::Result;
 use anyhow::anyhow;
 use clap::ArgMatches;
 use policy_server::PolicyServer;
+use policy_server::dry_run;
 use policy_server::metrics::setup_metrics;
 use policy_server::tracing::setup_tracing;
 
@@ -19,11 +22,37 @@ async fn main() -> Result<()> {
         .install_default()
         .expect("Failed to install crypto provider");
 
-    let matches = cli::build_cli().get_matches();
+    let mut matches = cli::build_cli().get_matches();
     if matches.subcommand_name() == Some("docs") {
         return run_docs_subcommand(matches.subcommand_matches("docs"));
     }
 
+    // The values of the configuration file are turned into command line flags,
+    // the arguments are then parsed again
+    let config_file_args = cli::config_file_args(&matches)?;
+    if !config_file_args.is_empty() {
+        matches = cli::build_cli()
+            .get_matches_from(env::args_os().chain(config_file_args.into_iter().map(Into::into)));
+    }
+
+    if matches.subcommand_name() == Some("show-config") {
+        print!("{}", cli::effective_config(&matches)?);
+        return Ok(());
+    }
+    if let Some(matches) = matches.subcommand_matches("test") {
+        return run_test_subcommand(matches).await;
+    }
+    if let Some(matches) = matches.subcommand_matches("check-config") {
+        return run_check_config_subcommand(matches).await;
+    }
+    if matches.subcommand_name() == Some("schema") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&policy_server::config::policies_json_schema())?
+        );
+        return Ok(());
+    }
+
     let config = policy_server::config::Config::from_args(&matches)?;
 
     let tracer_provider = setup_tracing(&config.log_level, &config.log_fmt, config.log_no_color)?;
@@ -76,3 +105,58 @@ fn run_docs_subcommand(matches: Option<&ArgMatches>) -> Result<()> {
     }
     Ok(())
 }
+
+/// Handle the test subcommand: evaluate a request against the policies and print the results,
+/// or run a test suite and print its report.
+/// The process exits with 1 when the request is not accepted by all the policies, or when
+/// a test case fails.
+async fn run_test_subcommand(matches: &ArgMatches) -> Result<()> {
+    let config = policy_server::config::Config::from_args(matches)?;
+
+    if let Some(suite_file) = matches.get_one::<PathBuf>("suite") {
+        let report = dry_run::test_suite::run_test_suite(config, suite_file).await?;
+        match matches
+            .get_one::<String>("output-format")
+            .expect("clap should have assigned a default value")
+            .as_str()
+        {
+            "junit" => print!("{}", report.to_junit()),
+            _ => print!("{}", report.to_tap()),
+        }
+
+        if !report.passed() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let request_file = matches
+        .get_one::<PathBuf>("request")
+        .expect("This should not happen, request is required when suite is not provided");
+    let policy_ids = matches
+        .get_many::<String>("policy")
+        .map(|policy_ids| policy_ids.cloned().collect());
+
+    let batch_review_response =
+        dry_run::evaluate_request_file(config, request_file, policy_ids).await?;
+    println!("{}", serde_json::to_string_pretty(&batch_review_response)?);
+
+    if !batch_review_response.all_allowed() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Handle the check-config subcommand: load the policies, validate them and print a report.
+/// The process exits with 1 when a policy is not valid.
+async fn run_check_config_subcommand(matches: &ArgMatches) -> Result<()> {
+    let config = policy_server::config::Config::from_args(matches)?;
+
+    let report = dry_run::check_config(config).await?;
+    print!("{report}");
+
+    if !report.passed() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -6,6 +6,16 @@ mod policy_evaluations_total;
 pub use policy_evaluations_total::add_policy_evaluation;
 mod policy_evaluations_latency;
 pub use policy_evaluations_latency::record_policy_latency;
+mod policy_group_monitored_rejections_total;
+pub(crate) use policy_group_monitored_rejections_total::add_monitored_group_member_rejection;
+mod policy_group_member_timeouts_total;
+pub(crate) use policy_group_member_timeouts_total::add_group_member_timeout;
+mod policy_evaluation_failures_total;
+pub(crate) use policy_evaluation_failures_total::add_policy_evaluation_failure;
+mod policy_evaluation_cache_requests_total;
+pub(crate) use policy_evaluation_cache_requests_total::add_evaluation_cache_request;
+mod policy_evaluations_queue_duration;
+pub(crate) use policy_evaluations_queue_duration::record_policy_queue_duration;
 
 use crate::config::build_client_tls_config_from_env;
 
@@ -41,6 +51,8 @@ pub(crate) struct PolicyEvaluation {
     pub(crate) mutated: bool,
     pub(crate) request_origin: String,
     pub(crate) error_code: Option<u16>,
+    /// Set when the request has been accepted without evaluating the policy
+    pub(crate) skip_reason: Option<String>,
 }
 
 impl PolicyEvaluationMetric for &PolicyEvaluation {}
@@ -69,6 +81,9 @@ impl Into<Vec<KeyValue>> for &PolicyEvaluation {
         if let Some(error_code) = self.error_code {
             baggage.append(&mut vec![KeyValue::new("error_code", error_code as i64)]);
         }
+        if let Some(skip_reason) = &self.skip_reason {
+            baggage.append(&mut vec![KeyValue::new("skip_reason", skip_reason.clone())]);
+        }
         baggage
     }
 }
@@ -118,3 +133,96 @@ impl Into<Vec<KeyValue>> for &PolicyInitializationError {
         ]
     }
 }
+
+/// A rejection made by a policy group member in monitor mode. The group considers the member
+/// as accepting the request.
+#[derive(Clone)]
+pub(crate) struct MonitoredGroupMemberRejection {
+    /// The ID of the member, like `group/member`
+    pub(crate) policy_name: String,
+}
+
+impl PolicyEvaluationMetric for &MonitoredGroupMemberRejection {}
+
+#[allow(clippy::from_over_into)]
+impl Into<Vec<KeyValue>> for &MonitoredGroupMemberRejection {
+    fn into(self) -> Vec<KeyValue> {
+        vec![KeyValue::new("policy_name", self.policy_name.clone())]
+    }
+}
+
+/// A policy group member whose evaluation timed out
+#[derive(Clone)]
+pub(crate) struct GroupMemberTimeout {
+    /// The ID of the member, like `group/member`
+    pub(crate) policy_name: String,
+    /// How the group treated the timeout: `allow`, `deny` or `error`
+    pub(crate) on_timeout: String,
+}
+
+impl PolicyEvaluationMetric for &GroupMemberTimeout {}
+
+#[allow(clippy::from_over_into)]
+impl Into<Vec<KeyValue>> for &GroupMemberTimeout {
+    fn into(self) -> Vec<KeyValue> {
+        vec![
+            KeyValue::new("policy_name", self.policy_name.clone()),
+            KeyValue::new("on_timeout", self.on_timeout.clone()),
+        ]
+    }
+}
+
+/// A policy evaluation failure handled by the failure policy of the policy
+#[derive(Clone)]
+pub(crate) struct PolicyEvaluationFailure {
+    pub(crate) policy_name: String,
+    /// The failure policy applied: `Ignore` or `Fail`
+    pub(crate) failure_policy: String,
+}
+
+impl PolicyEvaluationMetric for &PolicyEvaluationFailure {}
+
+#[allow(clippy::from_over_into)]
+impl Into<Vec<KeyValue>> for &PolicyEvaluationFailure {
+    fn into(self) -> Vec<KeyValue> {
+        vec![
+            KeyValue::new("policy_name", self.policy_name.clone()),
+            KeyValue::new("failure_policy", self.failure_policy.clone()),
+        ]
+    }
+}
+
+/// A lookup inside of the evaluation cache
+#[derive(Clone)]
+pub(crate) struct EvaluationCacheRequest {
+    pub(crate) policy_name: String,
+    /// Whether the response was found inside of the cache
+    pub(crate) hit: bool,
+}
+
+impl PolicyEvaluationMetric for &EvaluationCacheRequest {}
+
+#[allow(clippy::from_over_into)]
+impl Into<Vec<KeyValue>> for &EvaluationCacheRequest {
+    fn into(self) -> Vec<KeyValue> {
+        vec![
+            KeyValue::new("policy_name", self.policy_name.clone()),
+            KeyValue::new("result", if self.hit { "hit" } else { "miss" }),
+        ]
+    }
+}
+
+/// The time spent by a request waiting for the evaluation of a policy to start
+#[derive(Clone)]
+pub(crate) struct PolicyEvaluationQueue {
+    pub(crate) policy_name: String,
+}
+
+impl PolicyEvaluationMetric for &PolicyEvaluationQueue {}
+
+#[allow(clippy::from_over_into)]
+impl Into<Vec<KeyValue>> for &PolicyEvaluationQueue {
+    fn into(self) -> Vec<KeyValue> {
+        vec![KeyValue::new("policy_name", self.policy_name.clone())]
+    }
+}
@@ -0,0 +1,15 @@
+use lazy_static::lazy_static;
+use opentelemetry::{KeyValue, metrics::Counter};
+
+use crate::metrics::PolicyEvaluationMetric;
+
+lazy_static! {
+    static ref POLICY_EVALUATION_FAILURES_TOTAL: Counter<u64> =
+        opentelemetry::global::meter(super::METER_NAME)
+            .u64_counter("kubewarden_policy_evaluation_failures_total")
+            .build();
+}
+
+pub(crate) fn add_policy_evaluation_failure(failure: impl PolicyEvaluationMetric) {
+    POLICY_EVALUATION_FAILURES_TOTAL.add(1, &Into::<Vec<KeyValue>>::into(failure));
+}
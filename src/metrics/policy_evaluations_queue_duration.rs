@@ -0,0 +1,24 @@
+use lazy_static::lazy_static;
+use opentelemetry::{KeyValue, metrics::Histogram};
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use crate::metrics::PolicyEvaluationMetric;
+
+lazy_static! {
+    static ref POLICY_EVALUATION_QUEUE_DURATION: Histogram<u64> =
+        opentelemetry::global::meter(super::METER_NAME)
+            .u64_histogram("kubewarden_policy_evaluation_queue_duration_milliseconds")
+            .build();
+}
+
+pub(crate) fn record_policy_queue_duration(
+    duration: Duration,
+    policy_evaluation_queue: impl PolicyEvaluationMetric,
+) {
+    let millis_duration = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+    POLICY_EVALUATION_QUEUE_DURATION.record(
+        millis_duration,
+        &Into::<Vec<KeyValue>>::into(policy_evaluation_queue),
+    );
+}
@@ -0,0 +1,15 @@
+use lazy_static::lazy_static;
+use opentelemetry::{KeyValue, metrics::Counter};
+
+use crate::metrics::PolicyEvaluationMetric;
+
+lazy_static! {
+    static ref POLICY_GROUP_MEMBER_TIMEOUTS_TOTAL: Counter<u64> =
+        opentelemetry::global::meter(super::METER_NAME)
+            .u64_counter("kubewarden_policy_group_member_timeouts_total")
+            .build();
+}
+
+pub(crate) fn add_group_member_timeout(timeout: impl PolicyEvaluationMetric) {
+    POLICY_GROUP_MEMBER_TIMEOUTS_TOTAL.add(1, &Into::<Vec<KeyValue>>::into(timeout));
+}
@@ -0,0 +1,15 @@
+use lazy_static::lazy_static;
+use opentelemetry::{KeyValue, metrics::Counter};
+
+use crate::metrics::PolicyEvaluationMetric;
+
+lazy_static! {
+    static ref POLICY_GROUP_MONITORED_REJECTIONS_TOTAL: Counter<u64> =
+        opentelemetry::global::meter(super::METER_NAME)
+            .u64_counter("kubewarden_policy_group_monitored_rejections_total")
+            .build();
+}
+
+pub(crate) fn add_monitored_group_member_rejection(rejection: impl PolicyEvaluationMetric) {
+    POLICY_GROUP_MONITORED_REJECTIONS_TOTAL.add(1, &Into::<Vec<KeyValue>>::into(rejection));
+}
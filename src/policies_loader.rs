@@ -0,0 +1,292 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ::tracing::debug;
+use anyhow::{Result, anyhow};
+use policy_evaluator::{
+    callback_requests::CallbackRequest, policy_evaluator::PolicyEvaluatorPre,
+    policy_fetcher::verify::config::LatestVerificationConfig, wasmtime,
+};
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use tokio::{sync::mpsc, task};
+
+use crate::{
+    config::{Config, PolicyOrPolicyGroup},
+    evaluation::{
+        EvaluationEnvironment, EvaluationEnvironmentBuilder, PolicyEvaluatorPreKey,
+        always_accepted_namespaces::AlwaysAcceptedNamespaces,
+        evaluation_cache::EvaluationCache,
+        precompiled_policy::{PrecompiledPolicies, PrecompiledPolicy},
+    },
+    policy_downloader::{Downloader, FetchedPolicies},
+};
+
+/// Name of the directory, created inside of the policies download directory, that holds
+/// the precompiled Wasm modules
+const PRECOMPILED_MODULES_CACHE_DIR: &str = "precompiled-modules";
+
+/// Takes care of downloading and precompiling the policies defined by the user,
+/// then loads them into a new `EvaluationEnvironment`.
+///
+/// The loader is kept around for the whole life of the process, this allows the
+/// policies to be loaded again when their definition changes.
+///
+/// The loader keeps track of the Wasm modules it already compiled, indexed by the digest of
+/// their contents. When the policies are loaded again, all the modules are downloaded again:
+/// a mutable tag, or a local file, can point to a different module. Only the modules whose
+/// contents have not been seen before are compiled, the others are reused. This comes at the
+/// price of keeping the precompiled modules in memory.
+pub(crate) struct PoliciesLoader {
+    engine: wasmtime::Engine,
+    downloader: Downloader,
+    callback_handler_tx: mpsc::Sender<CallbackRequest>,
+    policies_download_dir: PathBuf,
+    verification_config: Option<LatestVerificationConfig>,
+    continue_on_errors: bool,
+    always_accepted_namespaces: AlwaysAcceptedNamespaces,
+    policy_evaluation_limit_seconds: Option<u64>,
+    /// The modules that have been successfully compiled, indexed by the sha256 digest of
+    /// the Wasm module
+    precompiled_policies: HashMap<String, PrecompiledPolicy>,
+    /// The `PolicyEvaluatorPre` instances of the last `EvaluationEnvironment` built
+    policy_evaluators_pre: HashMap<PolicyEvaluatorPreKey, Arc<PolicyEvaluatorPre>>,
+    /// The cache of the responses of the policies, shared by all the `EvaluationEnvironment`
+    /// instances built. `None` when the cache is disabled
+    evaluation_cache: Option<Arc<EvaluationCache>>,
+}
+
+impl PoliciesLoader {
+    pub fn new(
+        engine: wasmtime::Engine,
+        downloader: Downloader,
+        callback_handler_tx: mpsc::Sender<CallbackRequest>,
+        always_accepted_namespaces: AlwaysAcceptedNamespaces,
+        config: &Config,
+    ) -> Self {
+        Self {
+            engine,
+            downloader,
+            callback_handler_tx,
+            policies_download_dir: config.policies_download_dir.clone(),
+            verification_config: config.verification_config.clone(),
+            continue_on_errors: config.continue_on_errors,
+            always_accepted_namespaces,
+            policy_evaluation_limit_seconds: config.policy_evaluation_limit_seconds,
+            precompiled_policies: HashMap::new(),
+            policy_evaluators_pre: HashMap::new(),
+            evaluation_cache: config
+                .evaluation_cache_size
+                .map(|size| Arc::new(EvaluationCache::new(size))),
+        }
+    }
+
+    /// Download, precompile and load the given policies into a new `EvaluationEnvironment`
+    pub async fn load(
+        &mut self,
+        policies: &HashMap<String, PolicyOrPolicyGroup>,
+    ) -> Result<EvaluationEnvironment> {
+        let fetched_policies = self
+            .downloader
+            .download_policies(
+                policies,
+                &self.policies_download_dir,
+                self.verification_config.as_ref(),
+            )
+            .await;
+
+        // Compiling the Wasm modules and building the evaluation environment are CPU
+        // intensive operations, they must not block the async runtime
+        let engine = self.engine.clone();
+        let cache_dir = self
+            .policies_download_dir
+            .join(PRECOMPILED_MODULES_CACHE_DIR);
+        let known_modules = self.precompiled_policies.clone();
+        let (precompiled_policies, used_modules) = task::spawn_blocking(move || {
+            precompile_policies(&engine, &fetched_policies, &cache_dir, &known_modules)
+        })
+        .await?;
+
+        if !self.continue_on_errors {
+            for result in precompiled_policies.values() {
+                if let Err(error) = result {
+                    return Err(anyhow!(error.to_string()));
+                }
+            }
+        }
+
+        let engine = self.engine.clone();
+        let callback_handler_tx = self.callback_handler_tx.clone();
+        let continue_on_errors = self.continue_on_errors;
+        let always_accepted_namespaces = self.always_accepted_namespaces.clone();
+        let policy_evaluation_limit_seconds = self.policy_evaluation_limit_seconds;
+        let policy_evaluators_pre = self.policy_evaluators_pre.clone();
+        let evaluation_cache = self.evaluation_cache.clone();
+        let policies = policies.clone();
+
+        let evaluation_environment = task::spawn_blocking(move || {
+            let mut evaluation_environment_builder = EvaluationEnvironmentBuilder::new(
+                &engine,
+                &precompiled_policies,
+                callback_handler_tx,
+            )
+            .with_continue_on_errors(continue_on_errors)
+            .with_always_accepted_namespaces(always_accepted_namespaces)
+            .with_policy_evaluators_pre(policy_evaluators_pre);
+            if let Some(limit) = policy_evaluation_limit_seconds {
+                evaluation_environment_builder = evaluation_environment_builder
+                    .with_global_policy_evaluation_limit_seconds(limit);
+            }
+            if let Some(evaluation_cache) = evaluation_cache {
+                evaluation_environment_builder =
+                    evaluation_environment_builder.with_evaluation_cache(evaluation_cache);
+            }
+            evaluation_environment_builder.build(&policies)
+        })
+        .await??;
+
+        // Forget about the modules that are no longer used
+        self.precompiled_policies = used_modules;
+        self.policy_evaluators_pre = evaluation_environment.policy_evaluators_pre();
+
+        Ok(evaluation_environment)
+    }
+}
+
+/// Compile the fetched Wasm modules. The modules whose digest is included inside of
+/// `known_modules` are not compiled again, the previous compilation is reused.
+///
+/// Returns the precompiled policies, indexed by URL, together with the modules that have
+/// been successfully compiled, indexed by their digest.
+fn precompile_policies(
+    engine: &wasmtime::Engine,
+    fetched_policies: &FetchedPolicies,
+    cache_dir: &Path,
+    known_modules: &HashMap<String, PrecompiledPolicy>,
+) -> (PrecompiledPolicies, HashMap<String, PrecompiledPolicy>) {
+    debug!(
+        wasm_modules_count = fetched_policies.len(),
+        "instantiating wasmtime::Module objects"
+    );
+
+    let results: Vec<(String, Result<(String, PrecompiledPolicy)>)> = fetched_policies
+        .par_iter()
+        .map(|(policy_url, fetched_policy)| match fetched_policy {
+            Ok(policy) => {
+                let precompiled_policy =
+                    precompile_policy(engine, policy, cache_dir, known_modules);
+                debug!(?policy_url, "module compiled");
+                (policy_url.clone(), precompiled_policy)
+            }
+            Err(error) => (policy_url.clone(), Err(anyhow!(error.to_string()))),
+        })
+        .collect();
+
+    let mut precompiled_policies = PrecompiledPolicies::new();
+    let mut used_modules = HashMap::new();
+    for (policy_url, result) in results {
+        match result {
+            Ok((module_digest, precompiled_policy)) => {
+                used_modules.insert(module_digest, precompiled_policy.clone());
+                precompiled_policies.insert(policy_url, Ok(precompiled_policy));
+            }
+            Err(error) => {
+                precompiled_policies.insert(policy_url, Err(error));
+            }
+        }
+    }
+
+    (precompiled_policies, used_modules)
+}
+
+/// Compile the Wasm module, unless a module with the same digest is included inside of
+/// `known_modules`. Returns the digest of the Wasm module together with its compilation.
+fn precompile_policy(
+    engine: &wasmtime::Engine,
+    wasm_module_path: &Path,
+    cache_dir: &Path,
+    known_modules: &HashMap<String, PrecompiledPolicy>,
+) -> Result<(String, PrecompiledPolicy)> {
+    let mut hasher = Sha256::new();
+    hasher.update(fs::read(wasm_module_path)?);
+    let module_digest = format!("{:x}", hasher.finalize());
+
+    if let Some(precompiled_policy) = known_modules.get(&module_digest) {
+        debug!(
+            ?wasm_module_path,
+            "module not changed, reusing its compilation"
+        );
+        return Ok((module_digest, precompiled_policy.clone()));
+    }
+
+    PrecompiledPolicy::new(engine, wasm_module_path, Some(cache_dir))
+        .map(|precompiled_policy| (module_digest, precompiled_policy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use policy_evaluator::policy_evaluator::PolicyExecutionMode;
+
+    #[test]
+    fn only_modules_with_unknown_digest_are_compiled() {
+        let engine = wasmtime::Engine::default();
+        let download_dir = tempfile::tempdir().unwrap();
+        let known_module_path = download_dir.path().join("known.wasm");
+        fs::write(
+            &known_module_path,
+            include_bytes!("../tests/data/gatekeeper_always_happy_policy.wasm"),
+        )
+        .unwrap();
+        // Same URL as a module compiled before, but with different contents
+        let changed_module_path = download_dir.path().join("changed.wasm");
+        fs::write(
+            &changed_module_path,
+            include_bytes!("../tests/data/gatekeeper_always_unhappy_policy.wasm"),
+        )
+        .unwrap();
+
+        let mut hasher = Sha256::new();
+        hasher.update(include_bytes!(
+            "../tests/data/gatekeeper_always_happy_policy.wasm"
+        ));
+        let known_module_digest = format!("{:x}", hasher.finalize());
+        let known_precompiled_policy = PrecompiledPolicy {
+            precompiled_module: Vec::new(),
+            execution_mode: PolicyExecutionMode::OpaGatekeeper,
+            digest: "previous-compilation".to_owned(),
+        };
+        let known_modules =
+            HashMap::from([(known_module_digest.clone(), known_precompiled_policy)]);
+        let fetched_policies: FetchedPolicies = HashMap::from([
+            ("file:///known.wasm".to_owned(), Ok(known_module_path)),
+            ("file:///changed.wasm".to_owned(), Ok(changed_module_path)),
+        ]);
+
+        let (precompiled_policies, used_modules) = precompile_policies(
+            &engine,
+            &fetched_policies,
+            &download_dir.path().join(PRECOMPILED_MODULES_CACHE_DIR),
+            &known_modules,
+        );
+
+        assert_eq!(
+            precompiled_policies
+                .get("file:///known.wasm")
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .digest,
+            "previous-compilation"
+        );
+        assert!(!matches!(
+            precompiled_policies.get("file:///changed.wasm").unwrap(),
+            Ok(precompiled_policy) if precompiled_policy.digest == "previous-compilation"
+        ));
+        assert!(used_modules.contains_key(&known_module_digest));
+    }
+}
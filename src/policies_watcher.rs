@@ -0,0 +1,139 @@
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+use ::tracing::warn;
+use anyhow::Result;
+
+use crate::{
+    api::state::ApiServerState,
+    config::{PolicyOrPolicyGroup, SettingsInterpolation},
+    policies_loader::PoliciesLoader,
+};
+
+/// Time without events after which a burst of events is considered over
+#[cfg(target_os = "linux")]
+const RELOAD_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// There's no watching of the policies file on non-linux platforms
+/// since we rely on inotify to watch for changes
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn watch_policies_file_changes(
+    _policies_paths: Vec<PathBuf>,
+    _settings_interpolation: Option<SettingsInterpolation>,
+    _policies: HashMap<String, PolicyOrPolicyGroup>,
+    _policies_loader: PoliciesLoader,
+    _state: Arc<ApiServerState>,
+) -> Result<()> {
+    warn!("policies hot reload is supported only on linux");
+    Ok(())
+}
+
+/// Watch for changes of the policies files using inotify. When a file changes,
+/// the policies are downloaded and compiled again by the background task. The new
+/// `EvaluationEnvironment` then replaces the one in use, once it is fully built.
+///
+/// The directory containing each file is watched, instead of the file itself. This is
+/// required to detect the changes of files mounted from a Kubernetes ConfigMap: these
+/// files are symlinks that are atomically swapped by the kubelet. The directories given
+/// by the user are watched directly, this detects the files being added or removed.
+///
+/// Relying on inotify is only available on linux
+#[cfg(target_os = "linux")]
+pub(crate) fn watch_policies_file_changes(
+    policies_paths: Vec<PathBuf>,
+    settings_interpolation: Option<SettingsInterpolation>,
+    mut policies: HashMap<String, PolicyOrPolicyGroup>,
+    mut policies_loader: PoliciesLoader,
+    state: Arc<ApiServerState>,
+) -> Result<()> {
+    use ::tracing::{error, info};
+    use anyhow::anyhow;
+    use tokio::time;
+    use tokio_stream::StreamExt;
+
+    use crate::config::read_and_validate_policies;
+
+    let mut watched_dirs: Vec<PathBuf> = policies_paths
+        .iter()
+        .map(|path| {
+            if path.is_dir() {
+                return path.to_owned();
+            }
+            match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                _ => PathBuf::from("."),
+            }
+        })
+        .collect();
+    watched_dirs.sort();
+    watched_dirs.dedup();
+
+    let inotify =
+        inotify::Inotify::init().map_err(|e| anyhow!("Cannot initialize inotify: {e}"))?;
+    for watched_dir in &watched_dirs {
+        inotify
+            .watches()
+            .add(
+                watched_dir,
+                inotify::WatchMask::CLOSE_WRITE
+                    | inotify::WatchMask::MOVED_TO
+                    | inotify::WatchMask::DELETE,
+            )
+            .map_err(|e| anyhow!("Cannot watch policies directory {watched_dir:?}: {e}"))?;
+    }
+
+    let buffer = [0; 1024];
+    let stream = inotify
+        .into_event_stream(buffer)
+        .map_err(|e| anyhow!("Cannot create inotify event stream: {e}"))?;
+
+    tokio::spawn(async move {
+        tokio::pin!(stream);
+
+        while let Some(event) = stream.next().await {
+            if let Err(e) = event {
+                warn!("Cannot read inotify event: {e}");
+                continue;
+            }
+
+            // Editors and the kubelet produce a burst of events when updating a file.
+            // Consume the whole burst, until no event is received for a while, so that
+            // it triggers a single reload
+            while let Ok(Some(event)) = time::timeout(RELOAD_DEBOUNCE, stream.next()).await {
+                if let Err(e) = event {
+                    warn!("Cannot read inotify event: {e}");
+                }
+            }
+
+            let new_policies = match read_and_validate_policies(
+                &policies_paths,
+                settings_interpolation.as_ref(),
+            ) {
+                Ok(new_policies) => new_policies,
+                Err(e) => {
+                    error!(error = %e, "Cannot reload policies, keeping the current ones");
+                    continue;
+                }
+            };
+            if new_policies == policies {
+                continue;
+            }
+
+            info!(status = "init", "policies reload");
+            state.set_reloading(true);
+            let result = policies_loader.load(&new_policies).await;
+            state.set_reloading(false);
+            match result {
+                Ok(evaluation_environment) => {
+                    state.replace_evaluation_environment(evaluation_environment);
+                    policies = new_policies;
+                    info!(status = "done", "policies reload");
+                }
+                Err(e) => {
+                    error!(error = %e, "Cannot reload policies, keeping the current ones");
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
@@ -9,7 +9,10 @@ use policy_evaluator::admission_response_handler::policy_mode::PolicyMode;
 use policy_evaluator::policy_evaluator::PolicySettings;
 use policy_server::{
     PolicyServer,
-    config::{Config, PolicyGroupMember, PolicyOrPolicyGroup},
+    config::{
+        Config, ExpressionLanguage, MembersEvaluation, OnTimeout, PolicyGroupMember,
+        PolicyOrPolicyGroup,
+    },
 };
 use serde_json::json;
 use tempfile::tempdir;
@@ -42,6 +45,10 @@ pub(crate) fn default_test_config() -> Config {
                 context_aware_resources: BTreeSet::new(),
                 message: None,
                 timeout_eval_seconds: None,
+                failure_policy: None,
+                max_concurrency: None,
+                cache_ttl_seconds: None,
+                match_conditions: None,
             },
         ),
         (
@@ -60,6 +67,10 @@ pub(crate) fn default_test_config() -> Config {
                 context_aware_resources: BTreeSet::new(),
                 message: None,
                 timeout_eval_seconds: None,
+                failure_policy: None,
+                max_concurrency: None,
+                cache_ttl_seconds: None,
+                match_conditions: None,
             },
         ),
         (
@@ -77,12 +88,21 @@ pub(crate) fn default_test_config() -> Config {
                 ),
                 context_aware_resources: BTreeSet::new(),
                 message: None,
+                failure_policy: None,
+                max_concurrency: None,
+                cache_ttl_seconds: None,
+                match_conditions: None,
             },
         ),
         (
             "group-policy-just-pod-privileged".to_owned(),
             PolicyOrPolicyGroup::PolicyGroup {
                 expression: "pod_privileged() && true".to_string(),
+                expression_language: ExpressionLanguage::Rhai,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
                 message: "The group policy rejected your request".to_string(),
                 policy_mode: PolicyMode::Protect,
                 policies: HashMap::from([(
@@ -92,14 +112,24 @@ pub(crate) fn default_test_config() -> Config {
                         settings: None,
                         context_aware_resources: BTreeSet::new(),
                         timeout_eval_seconds: None,
+                        policy_mode: PolicyMode::Protect,
+                        on_timeout: OnTimeout::Error,
                     },
                 )]),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
             },
         ),
         (
             "group-policy-just-raw-mutation".to_owned(),
             PolicyOrPolicyGroup::PolicyGroup {
                 expression: "raw_mutation() && true".to_string(),
+                expression_language: ExpressionLanguage::Rhai,
+                members_evaluation: MembersEvaluation::Sequential,
+                allowed_to_mutate: None,
+                mutators: Vec::new(),
+                trace: false,
                 message: "The group policy rejected your request".to_string(),
                 policy_mode: PolicyMode::Protect,
                 policies: HashMap::from([(
@@ -115,8 +145,13 @@ pub(crate) fn default_test_config() -> Config {
                         ),
                         context_aware_resources: BTreeSet::new(),
                         timeout_eval_seconds: None,
+                        policy_mode: PolicyMode::Protect,
+                        on_timeout: OnTimeout::Error,
                     },
                 )]),
+                failure_policy: None,
+                max_concurrency: None,
+                match_conditions: None,
             },
         ),
         (
@@ -134,6 +169,10 @@ pub(crate) fn default_test_config() -> Config {
                 ),
                 context_aware_resources: BTreeSet::new(),
                 message: None,
+                failure_policy: None,
+                max_concurrency: None,
+                cache_ttl_seconds: None,
+                match_conditions: None,
             },
         ),
     ]);
@@ -143,9 +182,12 @@ pub(crate) fn default_test_config() -> Config {
         readiness_probe_addr: get_available_address_with_port(),
         sources: None,
         policies,
+        policies_paths: Vec::new(),
+        enable_policies_hot_reload: false,
         policies_download_dir: tempdir().unwrap().keep(),
         ignore_kubernetes_connection_failure: true,
-        always_accept_admission_reviews_on_namespace: None,
+        always_accept_admission_reviews_on_namespaces: Vec::new(),
+        always_accept_admission_reviews_on_namespace_selector: None,
         policy_evaluation_limit_seconds: Some(2),
         tls_config: None,
         pool_size: 2,
@@ -161,6 +203,11 @@ pub(crate) fn default_test_config() -> Config {
         daemon_stderr_file: None,
         enable_pprof: false,
         continue_on_errors: false,
+        readiness_max_policy_errors: None,
+        readiness_fail_on_reload: false,
+        redact_error_details: false,
+        evaluation_cache_size: None,
+        settings_interpolation: None,
     }
 }
 
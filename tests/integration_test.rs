@@ -3,7 +3,8 @@ mod common;
 use std::path::PathBuf;
 use std::{
     collections::{BTreeSet, HashMap},
-    time::Duration,
+    num::NonZeroUsize,
+    time::{Duration, Instant},
 };
 #[cfg(feature = "otel_tests")]
 use std::{fs::File, io::BufRead};
@@ -22,7 +23,18 @@ use policy_evaluator::{
     admission_response_handler::policy_mode::PolicyMode, policy_evaluator::PolicySettings,
     policy_fetcher::verify::config::VerificationConfigV1,
 };
-use policy_server::{api::admission_review::AdmissionReviewResponse, config::PolicyOrPolicyGroup};
+use policy_server::{
+    PolicyServer,
+    api::{
+        admission_review::AdmissionReviewResponse, api_error::ErrorCode,
+        batch_review::BatchReviewResponse,
+    },
+    config::{
+        ExpressionLanguage, FailurePolicy, MatchConditions, MembersEvaluation, OnTimeout,
+        PolicyGroupMember, PolicyOrPolicyGroup,
+    },
+    dry_run,
+};
 use regex::Regex;
 use rstest::*;
 use serde_json::json;
@@ -82,6 +94,10 @@ async fn test_validate_custom_rejection_message() {
             context_aware_resources: BTreeSet::new(),
             message: Some("Custom error message".to_owned()),
             timeout_eval_seconds: None,
+            failure_policy: None,
+            max_concurrency: None,
+            cache_ttl_seconds: None,
+            match_conditions: None,
         },
     );
     let app = app(config).await;
@@ -121,6 +137,75 @@ async fn test_validate_custom_rejection_message() {
     );
 }
 
+#[tokio::test]
+#[rstest]
+#[case::operation_not_matching(
+    MatchConditions {
+        operations: BTreeSet::from(["DELETE".to_owned()]),
+        ..Default::default()
+    },
+    true
+)]
+#[case::expression_not_matching(
+    MatchConditions {
+        expression: Some(r#"request.namespace != "default""#.to_owned()),
+        ..Default::default()
+    },
+    true
+)]
+#[case::matching(
+    MatchConditions {
+        operations: BTreeSet::from(["CREATE".to_owned()]),
+        kinds: BTreeSet::from(["Pod".to_owned()]),
+        expression: Some(r#"request.namespace == "default""#.to_owned()),
+        ..Default::default()
+    },
+    false
+)]
+async fn test_validate_match_conditions(
+    #[case] match_conditions: MatchConditions,
+    #[case] expected_allowed: bool,
+) {
+    setup();
+
+    let mut config = default_test_config();
+    config.policies.insert(
+        "pod-privileged".to_owned(),
+        PolicyOrPolicyGroup::Policy {
+            module: "ghcr.io/kubewarden/tests/pod-privileged:v0.2.1".to_owned(),
+            policy_mode: PolicyMode::Protect,
+            allowed_to_mutate: None,
+            settings: None,
+            context_aware_resources: BTreeSet::new(),
+            message: None,
+            timeout_eval_seconds: None,
+            failure_policy: None,
+            max_concurrency: None,
+            cache_ttl_seconds: None,
+            match_conditions: Some(match_conditions),
+        },
+    );
+    let app = app(config).await;
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .header(header::CONTENT_TYPE, "application/json")
+        .uri("/validate/pod-privileged")
+        .body(Body::from(include_str!(
+            "data/pod_with_privileged_containers.json"
+        )))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let admission_review_response: AdmissionReviewResponse =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+
+    assert_eq!(admission_review_response.response.allowed, expected_allowed);
+}
+
 #[tokio::test]
 #[rstest]
 #[case::pod_with_privileged_containers(
@@ -203,6 +288,10 @@ async fn test_validate_policy_not_found() {
     let response = app.oneshot(request).await.unwrap();
 
     assert_eq!(response.status(), 404);
+
+    let body: serde_json::Value =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+    assert_eq!(body["code"], "POLICY_NOT_FOUND");
 }
 
 #[tokio::test]
@@ -224,6 +313,257 @@ async fn test_validate_invalid_payload() {
     assert_eq!(response.status(), 422);
 }
 
+#[tokio::test]
+async fn test_validate_batch() {
+    setup();
+
+    let app = app(default_test_config()).await;
+
+    let admission_review: serde_json::Value =
+        serde_json::from_str(include_str!("data/pod_with_privileged_containers.json")).unwrap();
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .header(header::CONTENT_TYPE, "application/json")
+        .uri("/validate_batch")
+        .body(Body::from(
+            json!({
+                "admissionReview": admission_review,
+                "policies": ["pod-privileged", "does-not-exist"],
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    let batch_review_response: BatchReviewResponse =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+    assert_eq!(batch_review_response.results.len(), 2);
+
+    let pod_privileged = &batch_review_response.results[0];
+    assert_eq!(pod_privileged.policy_id, "pod-privileged");
+    assert!(!pod_privileged.response.as_ref().unwrap().allowed);
+    assert!(pod_privileged.error.is_none());
+
+    let does_not_exist = &batch_review_response.results[1];
+    assert_eq!(does_not_exist.policy_id, "does-not-exist");
+    assert!(does_not_exist.response.is_none());
+    assert_eq!(does_not_exist.error.as_ref().unwrap().status, 404);
+    assert_eq!(
+        does_not_exist.error.as_ref().unwrap().code,
+        ErrorCode::PolicyNotFound
+    );
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .header(header::CONTENT_TYPE, "application/json")
+        .uri("/validate_batch")
+        .body(Body::from(
+            json!({
+                "admissionReview": admission_review,
+                "policySelector": "^group-policy-",
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    let batch_review_response: BatchReviewResponse =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+    assert_eq!(
+        batch_review_response
+            .results
+            .iter()
+            .map(|result| result.policy_id.as_str())
+            .collect::<Vec<&str>>(),
+        vec![
+            "group-policy-just-pod-privileged",
+            "group-policy-just-raw-mutation"
+        ]
+    );
+}
+
+#[rstest]
+#[case::validate("validate", true)]
+#[case::audit("audit", false)]
+#[tokio::test]
+async fn test_validate_batch_origin(#[case] origin: &str, #[case] expected_allowed: bool) {
+    setup();
+
+    let mut config = default_test_config();
+    config.policies.insert(
+        "pod-privileged-monitor".to_owned(),
+        PolicyOrPolicyGroup::Policy {
+            module: "ghcr.io/kubewarden/tests/pod-privileged:v0.2.1".to_owned(),
+            policy_mode: PolicyMode::Monitor,
+            allowed_to_mutate: None,
+            settings: None,
+            context_aware_resources: BTreeSet::new(),
+            message: None,
+            timeout_eval_seconds: None,
+            failure_policy: None,
+            max_concurrency: None,
+            cache_ttl_seconds: None,
+            match_conditions: None,
+        },
+    );
+    let app = app(config).await;
+
+    let admission_review: serde_json::Value =
+        serde_json::from_str(include_str!("data/pod_with_privileged_containers.json")).unwrap();
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .header(header::CONTENT_TYPE, "application/json")
+        .uri("/validate_batch")
+        .body(Body::from(
+            json!({
+                "admissionReview": admission_review,
+                "policies": ["pod-privileged-monitor"],
+                "origin": origin,
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    // The policies in monitor mode accept the requests sent by the API server, the audit
+    // scanner gets the actual outcome of the evaluation
+    let batch_review_response: BatchReviewResponse =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+    assert_eq!(
+        batch_review_response.results[0]
+            .response
+            .as_ref()
+            .unwrap()
+            .allowed,
+        expected_allowed
+    );
+}
+
+#[tokio::test]
+async fn test_dry_run() {
+    setup();
+
+    let request_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data/pod_with_privileged_containers.json");
+
+    let batch_review_response = dry_run::evaluate_request_file(
+        default_test_config(),
+        &request_file,
+        Some(vec![
+            "pod-privileged".to_owned(),
+            "does-not-exist".to_owned(),
+        ]),
+    )
+    .await
+    .unwrap();
+
+    assert!(!batch_review_response.all_allowed());
+    assert_eq!(batch_review_response.results.len(), 2);
+    assert!(
+        !batch_review_response.results[0]
+            .response
+            .as_ref()
+            .unwrap()
+            .allowed
+    );
+    assert_eq!(
+        batch_review_response.results[1]
+            .error
+            .as_ref()
+            .unwrap()
+            .status,
+        404
+    );
+
+    let batch_review_response = dry_run::evaluate_request_file(
+        default_test_config(),
+        &request_file,
+        Some(vec!["sleep".to_owned()]),
+    )
+    .await
+    .unwrap();
+    assert!(batch_review_response.all_allowed());
+}
+
+#[tokio::test]
+async fn test_dry_run_test_suite() {
+    setup();
+
+    let suite_file = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/test_suite.yml");
+
+    let report = dry_run::test_suite::run_test_suite(default_test_config(), &suite_file)
+        .await
+        .unwrap();
+
+    assert!(!report.passed());
+    assert_eq!(
+        report
+            .results
+            .iter()
+            .map(|result| (result.name.as_str(), result.passed()))
+            .collect::<Vec<(&str, bool)>>(),
+        vec![
+            ("privileged pod is rejected", true),
+            ("unprivileged pod is accepted", true),
+            ("privileged pod is accepted", false),
+        ]
+    );
+    assert!(
+        report
+            .to_tap()
+            .contains("not ok 3 - privileged pod is accepted")
+    );
+}
+
+#[tokio::test]
+async fn test_check_config() {
+    setup();
+
+    let mut config = default_test_config();
+    config.policies.insert(
+        "invalid_settings".to_owned(),
+        PolicyOrPolicyGroup::Policy {
+            module: "ghcr.io/kubewarden/tests/sleeping-policy:v0.1.0".to_owned(),
+            policy_mode: PolicyMode::Protect,
+            allowed_to_mutate: None,
+            settings: Some(
+                PolicySettings::try_from(&json!({
+                    "sleepMilliseconds": "abc",
+                }))
+                .unwrap(),
+            ),
+            context_aware_resources: BTreeSet::new(),
+            message: None,
+            timeout_eval_seconds: None,
+            failure_policy: None,
+            max_concurrency: None,
+            cache_ttl_seconds: None,
+            match_conditions: None,
+        },
+    );
+
+    let report = dry_run::check_config(config).await.unwrap();
+
+    assert!(!report.passed());
+    assert_eq!(report.policies.get("pod-privileged"), Some(&None));
+    assert_eq!(
+        report.policies.get("group-policy-just-pod-privileged"),
+        Some(&None)
+    );
+    assert!(
+        report.policies["invalid_settings"]
+            .as_ref()
+            .unwrap()
+            .contains("Policy settings are invalid")
+    );
+}
+
 #[tokio::test]
 async fn test_validate_raw() {
     setup();
@@ -471,6 +811,7 @@ async fn test_timeout_protection_reject() {
                     .to_owned()
             ),
             code: Some(500),
+            reason: Some(ErrorCode::Timeout.to_string()),
             ..Default::default()
         })
     );
@@ -511,11 +852,227 @@ async fn test_timeout_protection_policy_specific_reject() {
                     .to_owned()
             ),
             code: Some(500),
+            reason: Some(ErrorCode::Timeout.to_string()),
             ..Default::default()
         })
     );
 }
 
+#[tokio::test]
+async fn test_timeout_protection_with_ignore_failure_policy() {
+    setup();
+
+    let mut config = default_test_config();
+    config.policies.insert(
+        "sleep-1s-timeout-ignore".to_owned(),
+        PolicyOrPolicyGroup::Policy {
+            module: "ghcr.io/kubewarden/tests/sleeping-policy:v0.1.0".to_owned(),
+            policy_mode: PolicyMode::Protect,
+            allowed_to_mutate: None,
+            settings: Some(
+                PolicySettings::try_from(&json!({
+                    "sleepMilliseconds": 2
+                }))
+                .unwrap(),
+            ),
+            context_aware_resources: BTreeSet::new(),
+            message: None,
+            timeout_eval_seconds: Some(1),
+            failure_policy: Some(FailurePolicy::Ignore),
+            max_concurrency: None,
+            cache_ttl_seconds: None,
+            match_conditions: None,
+        },
+    );
+
+    let app = app(config).await;
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .header(header::CONTENT_TYPE, "application/json")
+        .uri("/validate/sleep-1s-timeout-ignore")
+        .body(Body::from(include_str!("data/pod_sleep_4s.json")))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let admission_review_response: AdmissionReviewResponse =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+
+    assert!(admission_review_response.response.allowed);
+    let warnings = admission_review_response.response.warnings.unwrap();
+    assert!(
+        warnings[0].contains("Policy execution interrupted"),
+        "unexpected warnings: {warnings:?}"
+    );
+}
+
+#[tokio::test]
+#[rstest]
+#[case::allow(OnTimeout::Allow, PolicyMode::Protect)]
+#[case::deny(OnTimeout::Deny, PolicyMode::Protect)]
+#[case::error(OnTimeout::Error, PolicyMode::Protect)]
+#[case::error_in_monitor_mode(OnTimeout::Error, PolicyMode::Monitor)]
+async fn test_timeout_protection_policy_group_member(
+    #[case] on_timeout: OnTimeout,
+    #[case] member_policy_mode: PolicyMode,
+) {
+    setup();
+
+    let mut config = default_test_config();
+    config.policies.insert(
+        "group-policy-sleep".to_owned(),
+        PolicyOrPolicyGroup::PolicyGroup {
+            expression: "sleep()".to_string(),
+            expression_language: ExpressionLanguage::Rhai,
+            members_evaluation: MembersEvaluation::Sequential,
+            allowed_to_mutate: None,
+            mutators: Vec::new(),
+            trace: false,
+            message: "The group policy rejected your request".to_string(),
+            policy_mode: PolicyMode::Protect,
+            policies: HashMap::from([(
+                "sleep".to_string(),
+                PolicyGroupMember {
+                    module: "ghcr.io/kubewarden/tests/sleeping-policy:v0.1.0".to_owned(),
+                    settings: Some(
+                        PolicySettings::try_from(&json!({
+                            "sleepMilliseconds": 2
+                        }))
+                        .unwrap(),
+                    ),
+                    context_aware_resources: BTreeSet::new(),
+                    timeout_eval_seconds: Some(1),
+                    policy_mode: member_policy_mode.clone(),
+                    on_timeout,
+                },
+            )]),
+            failure_policy: None,
+            max_concurrency: None,
+            match_conditions: None,
+        },
+    );
+
+    let app = app(config).await;
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .header(header::CONTENT_TYPE, "application/json")
+        .uri("/validate/group-policy-sleep")
+        .body(Body::from(include_str!("data/pod_sleep_4s.json")))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let admission_review_response: AdmissionReviewResponse =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+    let status = admission_review_response.response.status;
+
+    // The members in monitor mode never change the outcome of the group
+    if matches!(member_policy_mode, PolicyMode::Monitor) {
+        assert!(admission_review_response.response.allowed);
+        assert_eq!(status, None);
+        return;
+    }
+    match on_timeout {
+        OnTimeout::Allow => {
+            assert!(admission_review_response.response.allowed);
+            assert_eq!(status, None);
+        }
+        OnTimeout::Deny => {
+            assert!(!admission_review_response.response.allowed);
+            let status = status.unwrap();
+            assert_eq!(
+                status.message,
+                Some("The group policy rejected your request".to_owned())
+            );
+            assert_eq!(
+                status.details.unwrap().causes,
+                vec![StatusCause {
+                    field: Some("spec.policies.sleep".to_owned()),
+                    message: Some("the evaluation of the policy timed out".to_owned()),
+                    ..Default::default()
+                }]
+            );
+        }
+        OnTimeout::Error => {
+            assert!(!admission_review_response.response.allowed);
+            let status = status.unwrap();
+            assert_eq!(status.code, Some(500));
+            assert_eq!(status.reason, Some(ErrorCode::Timeout.to_string()));
+            assert_eq!(
+                status.message,
+                Some(
+                    "group-policy-sleep: the evaluation of the policies [\"sleep\"] timed out"
+                        .to_owned()
+                )
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_max_concurrency() {
+    setup();
+
+    let mut config = default_test_config();
+    config.policies.insert(
+        "sleep-max-concurrency".to_owned(),
+        PolicyOrPolicyGroup::Policy {
+            module: "ghcr.io/kubewarden/tests/sleeping-policy:v0.1.0".to_owned(),
+            policy_mode: PolicyMode::Protect,
+            allowed_to_mutate: None,
+            settings: Some(
+                PolicySettings::try_from(&json!({
+                    "sleepMilliseconds": 2
+                }))
+                .unwrap(),
+            ),
+            context_aware_resources: BTreeSet::new(),
+            message: None,
+            timeout_eval_seconds: None,
+            failure_policy: None,
+            max_concurrency: NonZeroUsize::new(1),
+            cache_ttl_seconds: None,
+            match_conditions: None,
+        },
+    );
+
+    let app = app(config).await;
+
+    let build_request = || {
+        Request::builder()
+            .method(http::Method::POST)
+            .header(header::CONTENT_TYPE, "application/json")
+            .uri("/validate/sleep-max-concurrency")
+            .body(Body::from(include_str!("data/pod_sleep_100ms.json")))
+            .unwrap()
+    };
+
+    // Only one evaluation of the policy can run at a time, the second request waits
+    // for the first one to complete
+    let start = Instant::now();
+    let (first_response, second_response) = tokio::join!(
+        app.clone().oneshot(build_request()),
+        app.clone().oneshot(build_request())
+    );
+    assert!(start.elapsed() >= Duration::from_millis(200));
+
+    for response in [first_response.unwrap(), second_response.unwrap()] {
+        assert_eq!(response.status(), 200);
+
+        let admission_review_response: AdmissionReviewResponse =
+            serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes())
+                .unwrap();
+
+        assert!(admission_review_response.response.allowed);
+    }
+}
+
 #[tokio::test]
 async fn test_verified_policy() {
     setup();
@@ -556,6 +1113,10 @@ async fn test_verified_policy() {
             context_aware_resources: BTreeSet::new(),
             message: None,
             timeout_eval_seconds: None,
+            failure_policy: None,
+            max_concurrency: None,
+            cache_ttl_seconds: None,
+            match_conditions: None,
         },
     )]);
     config.verification_config = Some(verification_config);
@@ -595,6 +1156,10 @@ async fn test_policy_with_invalid_settings() {
             context_aware_resources: BTreeSet::new(),
             message: None,
             timeout_eval_seconds: None,
+            failure_policy: None,
+            max_concurrency: None,
+            cache_ttl_seconds: None,
+            match_conditions: None,
         },
     );
     config.continue_on_errors = true;
@@ -624,9 +1189,72 @@ async fn test_policy_with_invalid_settings() {
     let status = admission_review_response.response.status.unwrap();
 
     assert_eq!(status.code, Some(500));
+    assert_eq!(status.reason, Some(ErrorCode::SettingsInvalid.to_string()));
     assert!(pattern.is_match(&status.message.unwrap()));
 }
 
+#[tokio::test]
+#[rstest]
+#[case::fail(None, false)]
+#[case::ignore(Some(FailurePolicy::Ignore), true)]
+async fn test_policy_with_invalid_settings_and_not_matching_conditions(
+    #[case] failure_policy: Option<FailurePolicy>,
+    #[case] expected_allowed: bool,
+) {
+    setup();
+
+    let mut config = default_test_config();
+    config.policies.insert(
+        "invalid_settings".to_owned(),
+        PolicyOrPolicyGroup::Policy {
+            module: "ghcr.io/kubewarden/tests/sleeping-policy:v0.1.0".to_owned(),
+            policy_mode: PolicyMode::Protect,
+            allowed_to_mutate: None,
+            settings: Some(
+                PolicySettings::try_from(&json!({
+                    "sleepMilliseconds": "abc",
+                }))
+                .unwrap(),
+            ),
+            context_aware_resources: BTreeSet::new(),
+            message: None,
+            timeout_eval_seconds: None,
+            failure_policy,
+            max_concurrency: None,
+            cache_ttl_seconds: None,
+            match_conditions: Some(MatchConditions {
+                operations: BTreeSet::from(["DELETE".to_owned()]),
+                ..Default::default()
+            }),
+        },
+    );
+    config.continue_on_errors = true;
+
+    let app = app(config).await;
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .header(header::CONTENT_TYPE, "application/json")
+        .uri("/validate/invalid_settings")
+        .body(Body::from(include_str!("data/pod_sleep_100ms.json")))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    // The policy that could not be initialized is not skipped by its match conditions
+    assert_eq!(response.status(), 200);
+
+    let admission_review_response: AdmissionReviewResponse =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+
+    assert_eq!(admission_review_response.response.allowed, expected_allowed);
+    if !expected_allowed {
+        let status = admission_review_response.response.status.unwrap();
+        assert_eq!(status.code, Some(500));
+        assert_eq!(status.reason, Some(ErrorCode::SettingsInvalid.to_string()));
+    }
+}
+
 #[tokio::test]
 async fn test_policy_with_wrong_url() {
     setup();
@@ -642,6 +1270,10 @@ async fn test_policy_with_wrong_url() {
             context_aware_resources: BTreeSet::new(),
             message: None,
             timeout_eval_seconds: None,
+            failure_policy: None,
+            max_concurrency: None,
+            cache_ttl_seconds: None,
+            match_conditions: None,
         },
     );
     config.continue_on_errors = true;
@@ -671,9 +1303,244 @@ async fn test_policy_with_wrong_url() {
     let status = admission_review_response.response.status.unwrap();
 
     assert_eq!(status.code, Some(500));
+    assert_eq!(
+        status.reason,
+        Some(ErrorCode::PolicyInitializationFailed.to_string())
+    );
     assert!(pattern.is_match(&status.message.unwrap()));
 }
 
+#[tokio::test]
+async fn test_list_policies() {
+    setup();
+
+    let mut config = default_test_config();
+    config.policies.insert(
+        "wrong_url".to_owned(),
+        PolicyOrPolicyGroup::Policy {
+            module: "ghcr.io/kubewarden/tests/not_existing:v0.1.0".to_owned(),
+            policy_mode: PolicyMode::Monitor,
+            allowed_to_mutate: None,
+            settings: None,
+            context_aware_resources: BTreeSet::new(),
+            message: None,
+            timeout_eval_seconds: None,
+            failure_policy: None,
+            max_concurrency: None,
+            cache_ttl_seconds: None,
+            match_conditions: None,
+        },
+    );
+    config.continue_on_errors = true;
+
+    let app = app(config).await;
+
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri("/policies")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    let policies: serde_json::Value =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+    let policies = policies.as_array().unwrap();
+    assert_eq!(policies.len(), 7);
+
+    let wrong_url = policies
+        .iter()
+        .find(|policy| policy["id"] == "wrong_url")
+        .unwrap();
+    assert_eq!(
+        wrong_url["module"],
+        "ghcr.io/kubewarden/tests/not_existing:v0.1.0"
+    );
+    assert!(
+        wrong_url["initializationError"]
+            .as_str()
+            .unwrap()
+            .contains("Error while downloading policy 'wrong_url'")
+    );
+
+    let group = policies
+        .iter()
+        .find(|policy| policy["id"] == "group-policy-just-pod-privileged")
+        .unwrap();
+    assert_eq!(group["group"]["expression"], "pod_privileged() && true");
+    assert_eq!(
+        group["group"]["members"][0]["module"],
+        "ghcr.io/kubewarden/tests/pod-privileged:v0.2.1"
+    );
+
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri("/policies/sleep-1s-timeout")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    let policy: serde_json::Value =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+    assert_eq!(policy["policyMode"], "protect");
+    assert_eq!(policy["allowedToMutate"], false);
+    assert_eq!(policy["timeoutEvalSeconds"], 1);
+    assert!(policy["moduleDigest"].is_string());
+    assert!(policy.get("initializationError").is_none());
+
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri("/policies/does_not_exist")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+#[rstest]
+#[case::errors_tolerated(None, 200, true)]
+#[case::errors_not_tolerated(Some(0), 503, false)]
+async fn test_readiness_probe(
+    #[case] readiness_max_policy_errors: Option<usize>,
+    #[case] expected_status: u16,
+    #[case] expected_ready: bool,
+) {
+    setup();
+
+    let mut config = default_test_config();
+    config.policies.insert(
+        "wrong_url".to_owned(),
+        PolicyOrPolicyGroup::Policy {
+            module: "ghcr.io/kubewarden/tests/not_existing:v0.1.0".to_owned(),
+            policy_mode: PolicyMode::Protect,
+            allowed_to_mutate: None,
+            settings: None,
+            context_aware_resources: BTreeSet::new(),
+            message: None,
+            timeout_eval_seconds: None,
+            failure_policy: None,
+            max_concurrency: None,
+            cache_ttl_seconds: None,
+            match_conditions: None,
+        },
+    );
+    config.continue_on_errors = true;
+    config.readiness_max_policy_errors = readiness_max_policy_errors;
+
+    let readiness_probe_app = PolicyServer::new_from_config(config)
+        .await
+        .unwrap()
+        .readiness_probe_router();
+
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri("/readiness")
+        .body(Body::empty())
+        .unwrap();
+    let response = readiness_probe_app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), expected_status);
+
+    let readiness: serde_json::Value =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+    assert_eq!(readiness["ready"], expected_ready);
+    assert!(
+        readiness["policyInitializationErrors"]["wrong_url"]
+            .as_str()
+            .unwrap()
+            .contains("Error while downloading policy 'wrong_url'")
+    );
+
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri("/livez")
+        .body(Body::empty())
+        .unwrap();
+    let response = readiness_probe_app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_policies_hot_reload() {
+    setup();
+
+    let policies_dir = tempfile::tempdir().unwrap();
+    let policies_file = policies_dir.path().join("policies.yml");
+    let policies_yaml = r#"
+pod-privileged:
+  module: ghcr.io/kubewarden/tests/pod-privileged:v0.2.1
+"#;
+    fs::write(&policies_file, policies_yaml).await.unwrap();
+
+    let mut config = default_test_config();
+    config.policies = HashMap::from([(
+        "pod-privileged".to_owned(),
+        PolicyOrPolicyGroup::Policy {
+            module: "ghcr.io/kubewarden/tests/pod-privileged:v0.2.1".to_owned(),
+            policy_mode: PolicyMode::Protect,
+            allowed_to_mutate: None,
+            settings: None,
+            context_aware_resources: BTreeSet::new(),
+            message: None,
+            timeout_eval_seconds: None,
+            failure_policy: None,
+            max_concurrency: None,
+            cache_ttl_seconds: None,
+            match_conditions: None,
+        },
+    )]);
+    config.policies_paths = vec![policies_file.clone()];
+    config.enable_policies_hot_reload = true;
+
+    let app = app(config).await;
+
+    let build_request = || {
+        Request::builder()
+            .method(http::Method::POST)
+            .header(header::CONTENT_TYPE, "application/json")
+            .uri("/validate/pod-privileged-reloaded")
+            .body(Body::from(include_str!(
+                "data/pod_with_privileged_containers.json"
+            )))
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(build_request()).await.unwrap();
+    assert_eq!(response.status(), 404);
+
+    let policies_yaml = r#"
+pod-privileged:
+  module: ghcr.io/kubewarden/tests/pod-privileged:v0.2.1
+pod-privileged-reloaded:
+  module: ghcr.io/kubewarden/tests/pod-privileged:v0.2.1
+"#;
+    fs::write(&policies_file, policies_yaml).await.unwrap();
+
+    let exponential_backoff = ExponentialBuilder::default()
+        .with_min_delay(Duration::from_secs(1))
+        .with_max_delay(Duration::from_secs(10))
+        .with_max_times(5);
+
+    let response = (|| async {
+        let response = app.clone().oneshot(build_request()).await.unwrap();
+        if response.status() == 200 {
+            Ok(response)
+        } else {
+            Err(anyhow::anyhow!("policy not loaded yet"))
+        }
+    })
+    .retry(exponential_backoff)
+    .await
+    .expect("policies have not been reloaded");
+
+    let admission_review_response: AdmissionReviewResponse =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+
+    assert!(!admission_review_response.response.allowed);
+}
+
 // helper functions for certificate rotation test, which is a feature supported only on Linux
 #[cfg(target_os = "linux")]
 mod certificate_reload_helpers {